@@ -78,9 +78,13 @@ main() {
 
   # We need to publish one crate at a time...
 
+  cargo publish $args -p substreams-handler-spec
+  maybe_wait_publish
   cargo publish $args --target wasm32-unknown-unknown -p substreams-macro
   maybe_wait_publish
   cargo publish $args --target wasm32-unknown-unknown -p substreams
+  maybe_wait_publish
+  cargo publish $args -p substreams-manifest
 }
 
 cleanup_tag() {
@@ -0,0 +1,288 @@
+use crate::store::writable_store_signature;
+use std::collections::HashMap;
+
+/// The type of the `sf.substreams.v1.Clock` source.
+pub const CLOCK_SOURCE: &str = "sf.substreams.v1.Clock";
+
+/// The kind of a handler's input, which decides how the host passes it to the handler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    WritableStore,
+    ReadableStore,
+    Deltas,
+    Params,
+    Clock,
+    Proto,
+}
+
+impl InputKind {
+    /// Guesses the kind of an input from the segments of its type path, resolved through the
+    /// module's imports and type aliases, for tooling reading handlers without compiling them.
+    /// The handler macros don't guess, they build inputs through the `HandlerInput` trait.
+    ///
+    /// Only a type of the `substreams` crate is taken for a store, deltas or the clock: one
+    /// whose path starts with `substreams` or a bare name, glob imports not being resolved.
+    /// Any other type is a protobuf message, whatever its name.
+    pub fn from_type_path(segments: &[String]) -> InputKind {
+        let (type_name, parents) = match segments.split_last() {
+            Some(split) => split,
+            None => return InputKind::Proto,
+        };
+        if type_name == "String" {
+            return InputKind::Params;
+        }
+        if !parents.is_empty() && parents[0] != "substreams" {
+            return InputKind::Proto;
+        }
+
+        if writable_store_signature(type_name).is_some() {
+            InputKind::WritableStore
+        } else if type_name.starts_with("StoreGet") {
+            InputKind::ReadableStore
+        } else if type_name == "Deltas" || type_name == "LazyDeltas" {
+            InputKind::Deltas
+        } else if type_name == "Clock" {
+            InputKind::Clock
+        } else {
+            InputKind::Proto
+        }
+    }
+}
+
+/// A handler's input kind, as declared by its `#[params]` attribute or guessed from its type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Input {
+    pub kind: InputKind,
+    /// The last segment of the input's type, empty if it's not a path.
+    pub type_name: String,
+}
+
+impl Input {
+    /// Parses an input's kind from its `#[params]` attribute or from its type resolved through
+    /// `imports`, see [InputKind::from_type_path].
+    pub fn parse(pat_type: &syn::PatType, imports: &Imports) -> Input {
+        let segments = match &*pat_type.ty {
+            syn::Type::Path(p) => imports.resolve(&p.path),
+            _ => Vec::new(),
+        };
+        let type_name = segments.last().cloned().unwrap_or_default();
+
+        let kind = if is_params(pat_type) {
+            InputKind::Params
+        } else {
+            InputKind::from_type_path(&segments)
+        };
+
+        Input { kind, type_name }
+    }
+}
+
+/// Returns true if an input is the module's params: a `#[params]` argument or a `String`.
+pub fn is_params(pat_type: &syn::PatType) -> bool {
+    let is_string = match &*pat_type.ty {
+        syn::Type::Path(p) => {
+            p.qself.is_none()
+                && p.path
+                    .segments
+                    .last()
+                    .map_or(false, |last| last.ident == "String")
+        }
+        _ => false,
+    };
+    is_string
+        || pat_type
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("params"))
+}
+
+/// Returns true if an input of a store handler named `name` is a raw store index, read
+/// through `substreams::state`: a `u32` whose name ends with `_idx`.
+pub fn is_store_index(ty: &syn::Type, store_handler: bool, name: &str) -> bool {
+    let is_u32 = matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("u32"));
+    is_u32 && store_handler && name.ends_with("_idx")
+}
+
+/// Returns the name of the store read by a store input, `_idx` inputs reading the store named
+/// like them without the suffix.
+pub fn store_name(input: &str) -> &str {
+    input.strip_suffix("_idx").unwrap_or(input)
+}
+
+/// Imports are the full paths of the names imported by the `use` items of a module, glob
+/// imports excluded, and of the paths its `type` aliases stand for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Imports(HashMap<String, Vec<String>>);
+
+impl Imports {
+    pub fn from_items(items: &[syn::Item]) -> Self {
+        fn collect(tree: &syn::UseTree, prefix: &mut Vec<String>, imports: &mut Imports) {
+            match tree {
+                syn::UseTree::Path(path) => {
+                    prefix.push(path.ident.to_string());
+                    collect(&path.tree, prefix, imports);
+                    prefix.pop();
+                }
+                syn::UseTree::Name(name) => {
+                    let mut full = prefix.clone();
+                    full.push(name.ident.to_string());
+                    imports.0.insert(name.ident.to_string(), full);
+                }
+                syn::UseTree::Rename(rename) => {
+                    let mut full = prefix.clone();
+                    full.push(rename.ident.to_string());
+                    imports.0.insert(rename.rename.to_string(), full);
+                }
+                syn::UseTree::Group(group) => group
+                    .items
+                    .iter()
+                    .for_each(|tree| collect(tree, prefix, imports)),
+                syn::UseTree::Glob(_) => {}
+            }
+        }
+
+        let mut imports = Imports::default();
+        for item in items {
+            match item {
+                syn::Item::Use(item) => collect(&item.tree, &mut Vec::new(), &mut imports),
+                syn::Item::Type(syn::ItemType { ident, ty, .. }) => {
+                    if let syn::Type::Path(syn::TypePath { qself: None, path }) = &**ty {
+                        let segments = path
+                            .segments
+                            .iter()
+                            .map(|segment| segment.ident.to_string())
+                            .collect();
+                        imports.0.insert(ident.to_string(), segments);
+                    }
+                }
+                _ => {}
+            }
+        }
+        imports
+    }
+
+    /// Returns the segments of `path`, its first segment replaced by the imported or aliased
+    /// path as long as there is one.
+    pub fn resolve(&self, path: &syn::Path) -> Vec<String> {
+        let mut segments: Vec<String> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        if path.leading_colon.is_some() {
+            return segments;
+        }
+
+        // Bounded so that an import cycle can't loop forever
+        for _ in 0..=self.0.len() {
+            match segments.first().and_then(|first| self.0.get(first)) {
+                Some(full) if full.first() != segments.first() || full.len() > 1 => {
+                    segments.splice(..1, full.iter().cloned());
+                }
+                _ => break,
+            }
+        }
+        segments
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_store_index, Imports, Input, InputKind};
+
+    fn kind(arg: syn::FnArg, imports: &Imports) -> InputKind {
+        match arg {
+            syn::FnArg::Typed(arg) => Input::parse(&arg, imports).kind,
+            syn::FnArg::Receiver(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_input_kinds() {
+        let none = Imports::default();
+
+        assert_eq!(
+            kind(syn::parse_quote!(s: StoreAddBigInt), &none),
+            InputKind::WritableStore
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(s: StoreGetProto<pb::T>), &none),
+            InputKind::ReadableStore
+        );
+        assert_eq!(
+            kind(
+                syn::parse_quote!(d: substreams::store::LazyDeltas<BigInt>),
+                &none
+            ),
+            InputKind::Deltas
+        );
+        assert_eq!(kind(syn::parse_quote!(p: String), &none), InputKind::Params);
+        assert_eq!(
+            kind(syn::parse_quote!(#[params] p: Filter), &none),
+            InputKind::Params
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(c: substreams::clock::Clock), &none),
+            InputKind::Clock
+        );
+        assert_eq!(kind(syn::parse_quote!(c: Clock), &none), InputKind::Clock);
+
+        // Types outside of the `substreams` crate are protobuf messages, whatever their name
+        assert_eq!(
+            kind(syn::parse_quote!(c: pb::Clock), &none),
+            InputKind::Proto
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(s: pb::StoreGetRaw), &none),
+            InputKind::Proto
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(d: pb::Deltas), &none),
+            InputKind::Proto
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(t: (u32, u32)), &none),
+            InputKind::Proto
+        );
+
+        let file: syn::File = syn::parse_quote! {
+            use substreams::{clock::Clock as BlockClock, store::StoreAddBigInt};
+            use pb::Deltas;
+
+            type Balances = StoreAddBigInt;
+            type Counts = substreams::store::StoreGetInt64;
+        };
+        let imports = Imports::from_items(&file.items);
+        assert_eq!(
+            kind(syn::parse_quote!(c: BlockClock), &imports),
+            InputKind::Clock
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(b: Balances), &imports),
+            InputKind::WritableStore
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(c: Counts), &imports),
+            InputKind::ReadableStore
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(d: Deltas), &imports),
+            InputKind::Proto
+        );
+        assert_eq!(
+            kind(syn::parse_quote!(c: ::Balances), &imports),
+            InputKind::Proto
+        );
+    }
+
+    #[test]
+    fn test_store_index() {
+        let u32_type: syn::Type = syn::parse_quote!(u32);
+        let proto_type: syn::Type = syn::parse_quote!(pb::Prices);
+
+        assert!(is_store_index(&u32_type, true, "prices_idx"));
+        assert!(!is_store_index(&u32_type, false, "prices_idx"));
+        assert!(!is_store_index(&proto_type, true, "prices_idx"));
+        assert!(!is_store_index(&u32_type, true, "prices"));
+    }
+}
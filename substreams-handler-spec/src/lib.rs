@@ -0,0 +1,32 @@
+//! Rules shared by the Substreams handler macros and the manifest tooling.
+//!
+//! The `#[substreams::handlers::*]` macros and `substreams-manifest` both need to tell which
+//! handler inputs are params or raw store indexes, which the host passes differently from other
+//! inputs, and `substreams-manifest` the kind of the other inputs and the update policy and
+//! value type of a writable store from the handler's signature, without compiling it. Keeping
+//! these rules in this crate ensures a handler is read the same way when it's compiled and when
+//! its manifest is generated.
+//!
+//! ```rust
+//! use substreams_handler_spec::{writable_store_signature, Imports, Input, InputKind, UpdatePolicy};
+//!
+//! let func: syn::ItemFn = syn::parse_quote! {
+//!     fn store_balances(prices: substreams::store::StoreGetBigInt) {}
+//! };
+//! let arg = match &func.sig.inputs[0] {
+//!     syn::FnArg::Typed(arg) => arg,
+//!     syn::FnArg::Receiver(_) => unreachable!(),
+//! };
+//! let input = Input::parse(arg, &Imports::default());
+//! assert_eq!(input.kind, InputKind::ReadableStore);
+//!
+//! let (policy, value_kinds) = writable_store_signature("StoreAddBigInt").unwrap();
+//! assert_eq!(policy, UpdatePolicy::Add);
+//! assert_eq!(value_kinds, ["bigint"]);
+//! ```
+mod input;
+mod store;
+pub mod suggest;
+
+pub use input::{is_params, is_store_index, store_name, Imports, Input, InputKind, CLOCK_SOURCE};
+pub use store::{writable_store_signature, UpdatePolicy, ValueType};
@@ -0,0 +1,155 @@
+use crate::suggest::did_you_mean;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdatePolicy {
+    Set,
+    SetIfNotExists,
+    Add,
+    Min,
+    Max,
+    Append,
+}
+
+impl UpdatePolicy {
+    pub fn parse(s: &str) -> Result<UpdatePolicy, String> {
+        match s {
+            "set" => Ok(UpdatePolicy::Set),
+            "set_if_not_exists" => Ok(UpdatePolicy::SetIfNotExists),
+            "add" => Ok(UpdatePolicy::Add),
+            "min" => Ok(UpdatePolicy::Min),
+            "max" => Ok(UpdatePolicy::Max),
+            "append" => Ok(UpdatePolicy::Append),
+            _ => Err(format!(
+                "No such update policy `{}`. The update policies are `set`, `set_if_not_exists`, `add`, `min`, `max` and `append`.{}",
+                s,
+                did_you_mean(s, ["set", "set_if_not_exists", "add", "min", "max", "append"])
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdatePolicy::Set => "set",
+            UpdatePolicy::SetIfNotExists => "set_if_not_exists",
+            UpdatePolicy::Add => "add",
+            UpdatePolicy::Min => "min",
+            UpdatePolicy::Max => "max",
+            UpdatePolicy::Append => "append",
+        }
+    }
+}
+
+/// A store value type as written in the manifest, `proto:<message>` being kept whole.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueType(String);
+
+impl ValueType {
+    const KINDS: [&'static str; 6] = [
+        "int64",
+        "float64",
+        "bigint",
+        "bigdecimal",
+        "string",
+        "bytes",
+    ];
+
+    /// Accepted alternative names of value types, the manifest accepting both.
+    const ALIASES: [(&'static str, &'static str); 1] = [("bigfloat", "bigdecimal")];
+
+    pub fn parse(s: &str) -> Result<ValueType, String> {
+        let valid = Self::KINDS.contains(&s)
+            || Self::ALIASES.iter().any(|(alias, _)| *alias == s)
+            || s.strip_prefix("proto:").map_or(false, |x| !x.is_empty());
+
+        if !valid {
+            return Err(format!(
+                "No such value type `{}`. The value types are `int64`, `float64`, `bigint`, `bigdecimal`, `string`, `bytes` and `proto:<message>`.{}",
+                s,
+                did_you_mean(s, Self::KINDS)
+            ));
+        }
+
+        Ok(ValueType(s.to_string()))
+    }
+
+    /// Returns the value type without the message of `proto:<message>`, aliases resolved.
+    pub fn kind(&self) -> &str {
+        if self.0.starts_with("proto:") {
+            return "proto";
+        }
+
+        Self::ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == self.0)
+            .map_or(&self.0, |(_, kind)| kind)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Returns the update policy and the accepted value type kinds of a writable store type,
+/// an empty kinds list meaning any value type.
+pub fn writable_store_signature(
+    store_type: &str,
+) -> Option<(UpdatePolicy, &'static [&'static str])> {
+    let (policy, value) = if let Some(value) = store_type.strip_prefix("StoreSetIfNotExists") {
+        (UpdatePolicy::SetIfNotExists, value)
+    } else if let Some(value) = store_type.strip_prefix("StoreSet") {
+        (UpdatePolicy::Set, value)
+    } else if let Some(value) = store_type.strip_prefix("StoreAdd") {
+        (UpdatePolicy::Add, value)
+    } else if let Some(value) = store_type.strip_prefix("StoreMin") {
+        (UpdatePolicy::Min, value)
+    } else if let Some(value) = store_type.strip_prefix("StoreMax") {
+        (UpdatePolicy::Max, value)
+    } else if store_type == "StoreAppend" {
+        (UpdatePolicy::Append, "")
+    } else {
+        return None;
+    };
+
+    let kinds: &'static [&'static str] = match value {
+        "Raw" => &["bytes", "string"],
+        "String" => &["string"],
+        "Int64" => &["int64"],
+        "Float64" => &["float64"],
+        "BigInt" => &["bigint"],
+        "BigDecimal" => &["bigdecimal"],
+        "Proto" => &["proto"],
+        _ => &[],
+    };
+
+    Some((policy, kinds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{writable_store_signature, UpdatePolicy, ValueType};
+
+    #[test]
+    fn test_writable_store_signature() {
+        assert_eq!(
+            writable_store_signature("StoreSetIfNotExistsProto"),
+            Some((UpdatePolicy::SetIfNotExists, &["proto"][..]))
+        );
+        assert_eq!(
+            writable_store_signature("StoreSetRaw"),
+            Some((UpdatePolicy::Set, &["bytes", "string"][..]))
+        );
+        assert_eq!(
+            writable_store_signature("StoreAppend"),
+            Some((UpdatePolicy::Append, &[][..]))
+        );
+        assert_eq!(writable_store_signature("StoreGetInt64"), None);
+    }
+
+    #[test]
+    fn test_value_type() {
+        let bigfloat = ValueType::parse("bigfloat").unwrap();
+        assert_eq!(bigfloat.as_str(), "bigfloat");
+        assert_eq!(bigfloat.kind(), "bigdecimal");
+        assert_eq!(ValueType::parse("proto:my.Pool").unwrap().kind(), "proto");
+    }
+}
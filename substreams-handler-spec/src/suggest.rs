@@ -0,0 +1,71 @@
+/// Returns the candidate closest to `name` if it's likely to be a typo of it, comparing them
+/// case-insensitively.
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let name = name.to_lowercase();
+    let max_distance = std::cmp::max(1, name.chars().count() / 4);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns a ` Did you mean `<candidate>`?` sentence to append to an error message, empty if
+/// no candidate is close to `name`.
+pub fn did_you_mean<'a, I>(name: &str, candidates: I) -> String
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    match closest(name, candidates) {
+        Some(candidate) => format!(" Did you mean `{}`?", candidate),
+        None => "".to_string(),
+    }
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{closest, did_you_mean};
+
+    #[test]
+    fn test_closest() {
+        let policies = ["set", "set_if_not_exists", "add", "min", "max", "append"];
+
+        assert_eq!(closest("ad", policies), Some("add"));
+        assert_eq!(
+            closest("set_if_not_exist", policies),
+            Some("set_if_not_exists")
+        );
+        assert_eq!(closest("sum", policies), None);
+        assert_eq!(
+            closest("StoreGetBigint", ["StoreGetBigInt", "StoreGetInt64"]),
+            Some("StoreGetBigInt")
+        );
+        assert_eq!(
+            did_you_mean("outpt", ["name", "output"]),
+            " Did you mean `output`?"
+        );
+        assert_eq!(did_you_mean("foo", ["name", "output"]), "");
+    }
+}
@@ -1,108 +1,378 @@
-// use proc_macro2::{Span};
-// type AttributeArgs = syn::punctuated::Punctuated<syn::NestedMeta, syn::Token![,]>;
+use proc_macro2::Span;
+use substreams_handler_spec::suggest::did_you_mean;
+use substreams_handler_spec::{UpdatePolicy, ValueType};
+
+pub type AttributeArgs = syn::punctuated::Punctuated<syn::NestedMeta, syn::Token![,]>;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum ModuleType {
     Store,
     Map,
+    Index,
 }
 
 impl ModuleType {
-    // fn from_str(s: &str) -> Result<ModuleType, String> {
-    //     match s {
-    //         "store" => Ok(ModuleType::Store),
-    //         "map" => Ok(ModuleType::Map),
-    //         _ => Err(format!("No such module type `{}`. The modules types are `store` and `map`.", s)),
-    //     }
-    // }
+    fn attributes(&self) -> &'static [&'static str] {
+        match self {
+            ModuleType::Store => &[
+                "name",
+                "manifest",
+                "update_policy",
+                "value_type",
+                "writable_store",
+            ],
+            ModuleType::Map | ModuleType::Index => &["name", "manifest", "output"],
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModuleType::Store => "store",
+            ModuleType::Map => "map",
+            ModuleType::Index => "index",
+        }
+    }
 }
 
 pub struct FinalConfiguration {
     pub module_type: ModuleType,
+    pub name: Option<String>,
+    pub manifest: Option<(String, Span)>,
+    /// The declared `output` type and the span of the attribute argument.
+    pub output: Option<(String, Span)>,
+    pub update_policy: Option<(UpdatePolicy, Span)>,
+    pub value_type: Option<(ValueType, Span)>,
+    /// The span of `writable_store = "none"`, opting a store handler out of requiring a
+    /// writable store.
+    pub no_writable_store: Option<Span>,
+}
+
+impl FinalConfiguration {
+    /// The module's name in the manifest, the handler's name unless set with `name = "..."`.
+    pub fn module_name(&self, handler: &syn::Ident) -> String {
+        self.name.clone().unwrap_or_else(|| handler.to_string())
+    }
+
+    /// Checks that `update_policy` and `value_type`, which the writable store's type is checked
+    /// against by the generated code through `substreams::handlers::HandlerInput`, are not
+    /// declared on a handler without a writable store.
+    pub fn validate_writable_store(&self) -> Result<(), syn::Error> {
+        let declared_span = match (&self.update_policy, &self.value_type) {
+            (Some((_, span)), _) | (None, Some((_, span))) => *span,
+            (None, None) => return Ok(()),
+        };
+
+        match self.no_writable_store {
+            Some(_) => Err(syn::Error::new(
+                declared_span,
+                "`update_policy` and `value_type` require the handler to have a writable store as an input",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+struct Configuration {
+    module_type: ModuleType,
+    name: Option<String>,
+    manifest: Option<(String, Span)>,
+    output: Option<(String, Span)>,
+    update_policy: Option<(UpdatePolicy, Span)>,
+    value_type: Option<(ValueType, Span)>,
+    no_writable_store: Option<Span>,
+}
+
+impl Configuration {
+    fn new(module_type: ModuleType) -> Self {
+        Configuration {
+            module_type,
+            name: None,
+            manifest: None,
+            output: None,
+            update_policy: None,
+            value_type: None,
+            no_writable_store: None,
+        }
+    }
+
+    fn set_name(&mut self, name: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.name.is_some() {
+            return Err(syn::Error::new(span, "`name` set multiple times."));
+        }
+
+        let name = parse_string(name, span, "name")?;
+        let valid = name
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(syn::Error::new(
+                span,
+                format!("Module name `{}` must only contain ASCII letters, digits and `_` and not start with a digit.", name),
+            ));
+        }
+
+        self.name = Some(name);
+        Ok(())
+    }
+
+    fn set_manifest(&mut self, manifest: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.manifest.is_some() {
+            return Err(syn::Error::new(span, "`manifest` set multiple times."));
+        }
+
+        self.manifest = Some((parse_string(manifest, span, "manifest")?, span));
+        Ok(())
+    }
+
+    fn set_output(&mut self, output: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.output.is_some() {
+            return Err(syn::Error::new(span, "`output` set multiple times."));
+        }
+
+        let output = parse_string(output, span, "output")?;
+        if output != "bytes" && output.strip_prefix("proto:").map_or(true, str::is_empty) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "No such output type `{}`. The output types are `bytes` and `proto:<message>`.",
+                    output
+                ),
+            ));
+        }
+
+        self.output = Some((output, span));
+        Ok(())
+    }
+
+    fn set_update_policy(&mut self, policy: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.update_policy.is_some() {
+            return Err(syn::Error::new(span, "`update_policy` set multiple times."));
+        }
+
+        let policy_str = parse_string(policy, span, "update_policy")?;
+        let policy = UpdatePolicy::parse(&policy_str).map_err(|err| syn::Error::new(span, err))?;
+        self.update_policy = Some((policy, span));
+        Ok(())
+    }
+
+    fn set_value_type(&mut self, value_type: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.value_type.is_some() {
+            return Err(syn::Error::new(span, "`value_type` set multiple times."));
+        }
+
+        let value_type_str = parse_string(value_type, span, "value_type")?;
+        let value_type =
+            ValueType::parse(&value_type_str).map_err(|err| syn::Error::new(span, err))?;
+        self.value_type = Some((value_type, span));
+        Ok(())
+    }
+
+    fn set_writable_store(
+        &mut self,
+        writable_store: syn::Lit,
+        span: Span,
+    ) -> Result<(), syn::Error> {
+        if self.no_writable_store.is_some() {
+            return Err(syn::Error::new(
+                span,
+                "`writable_store` set multiple times.",
+            ));
+        }
+
+        let writable_store = parse_string(writable_store, span, "writable_store")?;
+        if writable_store != "none" {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "No such writable store setting `{}`. Only `none` is accepted, for store handlers not writing to a store.",
+                    writable_store
+                ),
+            ));
+        }
+
+        self.no_writable_store = Some(span);
+        Ok(())
+    }
+
+    fn build(self) -> Result<FinalConfiguration, syn::Error> {
+        Ok(FinalConfiguration {
+            module_type: self.module_type,
+            name: self.name,
+            manifest: self.manifest,
+            output: self.output,
+            update_policy: self.update_policy,
+            value_type: self.value_type,
+            no_writable_store: self.no_writable_store,
+        })
+    }
+}
+
+fn parse_string(lit: syn::Lit, span: Span, field: &str) -> Result<String, syn::Error> {
+    match lit {
+        syn::Lit::Str(s) => Ok(s.value()),
+        syn::Lit::Verbatim(s) => Ok(s.to_string()),
+        _ => Err(syn::Error::new(
+            span,
+            format!("Failed to parse value of `{}` as string.", field),
+        )),
+    }
+}
+
+pub fn build_config(
+    args: AttributeArgs,
+    module_type: ModuleType,
+) -> Result<FinalConfiguration, syn::Error> {
+    let mut config = Configuration::new(module_type);
+
+    for arg in args {
+        match arg {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(namevalue)) => {
+                let ident = namevalue
+                    .path
+                    .get_ident()
+                    .ok_or_else(|| {
+                        syn::Error::new_spanned(&namevalue, "Must have specified ident")
+                    })?
+                    .to_string()
+                    .to_lowercase();
+                let span = namevalue.lit.span();
+                let lit = namevalue.lit.clone();
+
+                if !module_type.attributes().contains(&ident.as_str()) {
+                    let msg = format!(
+                        "Unknown attribute {} is specified for a {} handler; expected one of: `{}`.{}",
+                        ident,
+                        module_type.as_str(),
+                        module_type.attributes().join("`, `"),
+                        did_you_mean(&ident, module_type.attributes().iter().copied()),
+                    );
+                    return Err(syn::Error::new_spanned(namevalue, msg));
+                }
+
+                match ident.as_str() {
+                    "name" => config.set_name(lit, span)?,
+                    "manifest" => config.set_manifest(lit, span)?,
+                    "output" => config.set_output(lit, span)?,
+                    "update_policy" => config.set_update_policy(lit, span)?,
+                    "value_type" => config.set_value_type(lit, span)?,
+                    "writable_store" => config.set_writable_store(lit, span)?,
+                    _ => unreachable!("attribute names are checked above"),
+                }
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "Unknown attribute inside the macro, expected `key = \"value\"` arguments",
+                ));
+            }
+        }
+    }
+    config.build()
 }
 
-// struct Configuration {
-//     module_type: Option<ModuleType>
-// }
-//
-// impl Configuration {
-//     fn new() -> Self {
-//         Configuration {
-//             module_type: None,
-//         }
-//     }
-//
-//     fn set_module_type(&mut self, runtime: syn::Lit, span: Span) -> Result<(), syn::Error> {
-//         if self.module_type.is_some() {
-//             return Err(syn::Error::new(span, "`type` set multiple times."));
-//         }
-//
-//         let runtime_str = parse_string(runtime, span, "type")?;
-//         let mod_type =
-//             ModuleType::from_str(&runtime_str).map_err(|err| syn::Error::new(span, err))?;
-//         self.module_type = Some(mod_type);
-//         Ok(())
-//     }
-//
-//     fn build(&self) -> Result<FinalConfiguration, syn::Error> {
-//         let mod_type = self.module_type.unwrap_or(ModuleType::Map);
-//         Ok(FinalConfiguration {
-//             module_type: mod_type,
-//         })
-//     }
-// }
-
-// fn parse_string(int: syn::Lit, span: Span, field: &str) -> Result<String, syn::Error> {
-//     match int {
-//         syn::Lit::Str(s) => Ok(s.value()),
-//         syn::Lit::Verbatim(s) => Ok(s.to_string()),
-//         _ => Err(syn::Error::new(
-//             span,
-//             format!("Failed to parse value of `{}` as string.", field),
-//         )),
-//     }
-// }
-
-// fn build_config(
-//     args: AttributeArgs,
-// ) -> Result<FinalConfiguration, syn::Error> {
-//
-//     let mut config = Configuration::new();
-//
-//     for arg in args {
-//         match arg {
-//             syn::NestedMeta::Meta(syn::Meta::NameValue(namevalue)) => {
-//                 let ident = namevalue
-//                     .path
-//                     .get_ident()
-//                     .ok_or_else(|| {
-//                         syn::Error::new_spanned(&namevalue, "Must have specified ident")
-//                     })?
-//                     .to_string()
-//                     .to_lowercase();
-//                 match ident.as_str() {
-//                     "type" => {
-//                         config.set_module_type(
-//                             namevalue.lit.clone(),
-//                             syn::spanned::Spanned::span(&namevalue.lit),
-//                         )?;
-//                     }
-//                     name => {
-//                         let msg = format!(
-//                             "Unknown attribute {} is specified; expected one of: `type`",
-//                             name,
-//                         );
-//                         return Err(syn::Error::new_spanned(namevalue, msg));
-//                     }
-//                 }
-//             }
-//             other => {
-//                 return Err(syn::Error::new_spanned(
-//                     other,
-//                     "Unknown attribute inside the macro",
-//                 ));
-//             }
-//         }
-//     }
-//     config.build()
-// }
+#[cfg(test)]
+mod test {
+    use super::{build_config, AttributeArgs, ModuleType, UpdatePolicy};
+    use quote::quote;
+    use syn::parse::Parser;
+
+    fn config(
+        args: proc_macro2::TokenStream,
+        module_type: ModuleType,
+    ) -> Result<super::FinalConfiguration, syn::Error> {
+        build_config(
+            AttributeArgs::parse_terminated.parse2(args).unwrap(),
+            module_type,
+        )
+    }
+
+    #[test]
+    fn test_build_config() {
+        let map = config(
+            quote! { name = "map_pools", output = "proto:my.Pools" },
+            ModuleType::Map,
+        )
+        .unwrap();
+        assert_eq!(map.name.as_deref(), Some("map_pools"));
+        assert_eq!(
+            map.output.as_ref().map(|(output, _)| output.as_str()),
+            Some("proto:my.Pools")
+        );
+
+        let store = config(
+            quote! { update_policy = "add", value_type = "bigint" },
+            ModuleType::Store,
+        )
+        .unwrap();
+        assert_eq!(store.update_policy.unwrap().0, UpdatePolicy::Add);
+        assert_eq!(store.value_type.unwrap().0.as_str(), "bigint");
+
+        assert!(store.no_writable_store.is_none());
+
+        let store = config(quote! { writable_store = "none" }, ModuleType::Store).unwrap();
+        assert!(store.no_writable_store.is_some());
+    }
+
+    #[test]
+    fn test_build_config_errors() {
+        let error = |args, module_type| config(args, module_type).err().unwrap().to_string();
+
+        assert_eq!(
+            error(quote! { update_policy = "add" }, ModuleType::Map),
+            "Unknown attribute update_policy is specified for a map handler; expected one of: `name`, `manifest`, `output`."
+        );
+        assert_eq!(
+            error(quote! { output = "json" }, ModuleType::Map),
+            "No such output type `json`. The output types are `bytes` and `proto:<message>`."
+        );
+        assert_eq!(
+            error(quote! { name = "a", name = "b" }, ModuleType::Map),
+            "`name` set multiple times."
+        );
+        assert_eq!(
+            error(quote! { name = "map-pools" }, ModuleType::Map),
+            "Module name `map-pools` must only contain ASCII letters, digits and `_` and not start with a digit."
+        );
+        assert_eq!(
+            error(quote! { outpt = "bytes" }, ModuleType::Map),
+            "Unknown attribute outpt is specified for a map handler; expected one of: `name`, `manifest`, `output`. Did you mean `output`?"
+        );
+        assert!(error(quote! { update_policy = "sum" }, ModuleType::Store)
+            .starts_with("No such update policy `sum`."));
+        assert!(error(quote! { update_policy = "ad" }, ModuleType::Store)
+            .ends_with("Did you mean `add`?"));
+        assert!(error(quote! { value_type = "BigInt" }, ModuleType::Store)
+            .ends_with("Did you mean `bigint`?"));
+        assert!(error(quote! { value_type = "proto:" }, ModuleType::Store)
+            .starts_with("No such value type `proto:`."));
+        assert!(error(quote! { writable_store = "all" }, ModuleType::Store)
+            .starts_with("No such writable store setting `all`."));
+        assert!(error(quote! { writable_store = "none" }, ModuleType::Map)
+            .starts_with("Unknown attribute writable_store is specified for a map handler"));
+    }
+
+    #[test]
+    fn test_validate_writable_store() {
+        let store = config(
+            quote! { update_policy = "add", value_type = "bigint" },
+            ModuleType::Store,
+        )
+        .unwrap();
+        assert!(store.validate_writable_store().is_ok());
+
+        let store = config(
+            quote! { update_policy = "add", writable_store = "none" },
+            ModuleType::Store,
+        )
+        .unwrap();
+        assert_eq!(
+            store.validate_writable_store().unwrap_err().to_string(),
+            "`update_policy` and `value_type` require the handler to have a writable store as an input"
+        );
+
+        let store = config(quote! { writable_store = "none" }, ModuleType::Store).unwrap();
+        assert!(store.validate_writable_store().is_ok());
+    }
+}
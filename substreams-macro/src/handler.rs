@@ -1,30 +1,47 @@
-use crate::config::{FinalConfiguration, ModuleType};
-use crate::errors;
+use crate::config::{build_config, AttributeArgs, FinalConfiguration, ModuleType};
+use crate::manifest::{self, ModuleDeclaration};
 use proc_macro2::{Span, TokenStream};
-use quote::{format_ident, quote, ToTokens};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use substreams_handler_spec::{is_params, is_store_index, store_name};
+use syn::parse::Parser;
 use syn::spanned::Spanned;
 
-pub fn main(item: TokenStream, module_type: ModuleType) -> TokenStream {
-    let original = item.clone();
+pub fn main(args: TokenStream, item: TokenStream, module_type: ModuleType) -> TokenStream {
+    let original = strip_params_attributes(item.clone());
 
-    let final_config = FinalConfiguration { module_type };
-    let input = syn::parse2::<syn::ItemFn>(item).expect("Proc macro input should be a function");
-
-    let output_result = parse_func_output(&final_config, input.sig.output.clone());
-    let output_type;
-    match output_result {
-        Ok(t) => {
-            output_type = t;
+    let final_config = match AttributeArgs::parse_terminated
+        .parse2(args)
+        .and_then(|args| build_config(args, module_type))
+    {
+        Ok(config) => config,
+        Err(e) => return token_stream_with_error(original, e),
+    };
+    let input = match syn::parse2::<syn::ItemFn>(item) {
+        Ok(input) => input,
+        Err(e) => {
+            let message = format!(
+                "#[substreams::handlers::{}] can only be applied to a function",
+                module_type.as_str()
+            );
+            return token_stream_with_error(original, syn::Error::new(e.span(), message));
         }
+    };
+
+    let output_type = match parse_func_output(&final_config, &input.sig) {
+        Ok(t) => t,
         Err(e) => return token_stream_with_error(original, e),
+    };
+    if let Err(e) = validate_output(&final_config, &input.sig) {
+        return token_stream_with_error(original, e);
+    }
+    if let Err(e) = final_config.validate_writable_store() {
+        return token_stream_with_error(original, e);
     }
-    let mut has_seen_writable_store = false;
+
+    let handler_name = final_config.module_name(&input.sig.ident);
     let mut args: Vec<proc_macro2::TokenStream> = Vec::with_capacity(input.sig.inputs.len() * 2);
-    let mut proto_decodings: Vec<proc_macro2::TokenStream> =
-        Vec::with_capacity(input.sig.inputs.len());
-    let mut read_only_stores: Vec<proc_macro2::TokenStream> =
-        Vec::with_capacity(input.sig.inputs.len());
-    let mut writable_store: proc_macro2::TokenStream = quote! {};
+    let mut decodings: Vec<proc_macro2::TokenStream> = Vec::with_capacity(input.sig.inputs.len());
+    let mut arguments: Vec<Argument> = Vec::with_capacity(input.sig.inputs.len());
 
     for i in (&input.sig.inputs).into_iter() {
         match i {
@@ -33,213 +50,148 @@ pub fn main(item: TokenStream, module_type: ModuleType) -> TokenStream {
                     original,
                     syn::Error::new(
                         i.span(),
-                        format!("handler function does not support 'self' receiver"),
+                        "handler function does not support 'self' receiver",
                     ),
                 );
             }
             syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
                 syn::Pat::Ident(v) => {
                     let var_name = v.ident.clone();
-
+                    let mutability = v.mutability;
                     let argument_type = &*pat_type.ty;
-                    let input_obj = match parse_input_type(argument_type) {
-                        Ok(t) => t,
-                        Err(e) => {
-                            return token_stream_with_error(
-                                original,
-                                syn::Error::new(
-                                    pat_type.span(),
-                                    format!("failed to parse input {:?}", e),
-                                ),
-                            )
-                        }
-                    };
 
-                    if input_obj.is_writable_store {
-                        if has_seen_writable_store {
-                            return token_stream_with_error(
-                                    original,
-                                    syn::Error::new(pat_type.span(), format!("handler cannot have more then one writable store as an input"))
-                                );
-                        }
-                        has_seen_writable_store = true;
-                        let store_type = format_ident!("{}", input_obj.store_type);
-                        writable_store =
-                            quote! { let #var_name: #argument_type = #store_type::new(); };
-                        continue;
-                    }
-
-                    if input_obj.is_readable_store {
-                        let var_idx = format_ident!("{}_idx", var_name);
-                        let store_type = format_ident!("{}", input_obj.store_type);
-                        args.push(quote! { #var_idx: u32 });
-                        read_only_stores.push(
-                            quote! { let #var_name: #argument_type = #store_type::new(#var_idx); },
-                        );
+                    // A raw `u32` store index of a store handler, read through `substreams::state`
+                    if is_store_index(
+                        argument_type,
+                        final_config.module_type == ModuleType::Store,
+                        &var_name.to_string(),
+                    ) {
+                        args.push(quote! { #mutability #var_name: #argument_type });
+                        arguments.push(Argument::new(
+                            pat_type,
+                            &var_name,
+                            quote! { substreams::handlers::InputKind::ReadableStore },
+                        ));
                         continue;
                     }
 
-                    if final_config.module_type == ModuleType::Store
-                        && var_name.to_string().ends_with("_idx")
-                    {
-                        args.push(quote! { #pat_type });
-                        continue;
-                    }
                     let var_ptr = format_ident!("{}_ptr", var_name);
                     let var_len = format_ident!("{}_len", var_name);
-                    args.push(quote! { #var_ptr: *mut u8 });
-                    args.push(quote! { #var_len: usize });
-
-                    if input_obj.is_deltas {
-                        let raw = format_ident!("raw_{}", var_name);
-                        proto_decodings.push(quote! {
-                                let #raw = substreams::proto::decode_ptr::<substreams::pb::substreams::StoreDeltas>(#var_ptr, #var_len).unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to 'substreams::pb::substreams::StoreDeltas' message's struct", #var_len)).deltas;
-                                let #var_name: #argument_type = substreams::store::Deltas::new(#raw);
-                            })
-                    } else if input_obj.is_string {
-                        proto_decodings.push(quote! { let #var_name: String = std::mem::ManuallyDrop::new(unsafe {String::from_raw_parts(#var_ptr, #var_len, #var_len)}).to_string(); });
-                    } else {
-                        let mutability = if v.mutability.is_some() {
-                            quote! { mut }
-                        } else {
-                            quote! {}
-                        };
 
-                        proto_decodings.push(quote! { let #mutability #var_name: #argument_type = substreams::proto::decode_ptr(#var_ptr, #var_len).unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", #var_len, stringify!(#argument_type))); })
+                    if is_params(pat_type) {
+                        args.push(quote! { #var_ptr: *mut u8 });
+                        args.push(quote! { #var_len: usize });
+                        let decode = quote_spanned! {argument_type.span()=>
+                            substreams::params::decode_ptr(#var_ptr, #var_len)
+                        };
+                        decodings.push(quote! { let #mutability #var_name: #argument_type = #decode.unwrap_or_else(|err| substreams::errors::report_handler_error(#handler_name, err)); });
+                        arguments.push(Argument::new(
+                            pat_type,
+                            &var_name,
+                            quote! { substreams::handlers::InputKind::Params },
+                        ));
+                        continue;
                     }
+
+                    // Any other input is built through `HandlerInput`, the host passing it as
+                    // `Ptr` and `Len`, zero-sized for the writable store it doesn't pass
+                    let handler_input = quote_spanned! {argument_type.span()=>
+                        <#argument_type as substreams::handlers::HandlerInput>
+                    };
+                    args.push(
+                        quote_spanned! {argument_type.span()=> #var_ptr: #handler_input::Ptr },
+                    );
+                    args.push(
+                        quote_spanned! {argument_type.span()=> #var_len: #handler_input::Len },
+                    );
+                    decodings.push(quote! { let #mutability #var_name: #argument_type = #handler_input::from_host(#var_ptr, #var_len).unwrap_or_else(|err| substreams::errors::report_handler_error(#handler_name, err)); });
+                    arguments.push(Argument::new(
+                        pat_type,
+                        &var_name,
+                        quote! { #handler_input::KIND },
+                    ));
                 }
                 _ => {
                     return token_stream_with_error(
-                        original,
-                        syn::Error::new(pat_type.span(), format!("unknown argument type")),
-                    );
+                            original,
+                            syn::Error::new_spanned(
+                                &pat_type.pat,
+                                "handler arguments must be plain identifiers, destructure the input in the handler's body instead",
+                            ),
+                        );
                 }
             },
         }
     }
 
-    match final_config.module_type {
-        ModuleType::Store => build_store_handler(
-            input,
-            args,
-            proto_decodings,
-            read_only_stores,
-            writable_store,
-        ),
-        ModuleType::Map => {
+    let manifest = match manifest::validate_handler(&final_config, &input.sig.ident) {
+        Ok(manifest) => manifest,
+        Err(e) => return token_stream_with_error(original, e),
+    };
+    let assertions = input_assertions(
+        &arguments,
+        &final_config,
+        manifest.as_ref().map(|(_, declared)| declared),
+        &input.sig.ident,
+    );
+    // Makes Cargo track the manifest, so changing it validates the handler again
+    let track_manifest = manifest.map(|(path, _)| {
+        let path = path.display().to_string();
+        quote! { const _: &str = include_str!(#path); }
+    });
+
+    let handler = match final_config.module_type {
+        ModuleType::Store => {
+            build_store_handler(input, &final_config, output_type, args, decodings)
+        }
+        ModuleType::Map | ModuleType::Index => {
             if output_type == OutputType::Void {
+                let message = if final_config.module_type == ModuleType::Index {
+                    "index handler must return a value"
+                } else {
+                    "map handler must return a value"
+                };
+
                 return token_stream_with_error(
                     original,
-                    syn::Error::new(
-                        input.sig.output.span(),
-                        format!("map handler must return a value"),
-                    ),
+                    syn::Error::new(input.sig.output.span(), message),
                 );
             }
 
-            build_map_handler(
-                input,
-                output_type,
-                args,
-                proto_decodings,
-                read_only_stores,
-                writable_store,
-            )
+            build_map_handler(input, &final_config, output_type, args, decodings)
         }
+    };
+
+    quote! {
+        #handler
+        #assertions
+        #track_manifest
     }
 }
 
-const WRITABLE_STORE: [&'static str; 27] = [
-    "StoreSetRaw",
-    "StoreSetString",
-    "StoreSetBigInt",
-    "StoreSetBigDecimal",
-    "StoreSetProto",
-    "StoreSetInt64",
-    "StoreSetFloat64",
-    "StoreSetIfNotExistsRaw",
-    "StoreSetIfNotExistsString",
-    "StoreSetIfNotExistsBigDecimal",
-    "StoreSetIfNotExistsBigInt",
-    "StoreSetIfNotExistsInt64",
-    "StoreSetIfNotExistsFloat64",
-    "StoreSetIfNotExistsProto",
-    "StoreAddInt64",
-    "StoreAddFloat64",
-    "StoreAddBigDecimal",
-    "StoreAddBigInt",
-    "StoreMaxInt64",
-    "StoreMaxBigInt",
-    "StoreMaxFloat64",
-    "StoreMaxBigDecimal",
-    "StoreMinInt64",
-    "StoreMinBigInt",
-    "StoreMinFloat64",
-    "StoreMinBigDecimal",
-    "StoreAppend",
-];
-
-const READABLE_STORE: [&'static str; 8] = [
-    "StoreGetInt64",
-    "StoreGetFloat64",
-    "StoreGetBigDecimal",
-    "StoreGetBigInt",
-    "StoreGetProto",
-    "StoreGetRaw",
-    "StoreGetString",
-    "StoreGetArray",
-];
-
-#[derive(Debug)]
-struct Input {
-    is_writable_store: bool,
-    is_readable_store: bool,
-    is_deltas: bool,
-    is_string: bool,
-    resolved_ty: String,
-    store_type: String,
+/// A handler's argument, with the expression of its `substreams::handlers::InputKind`.
+struct Argument {
+    name: String,
+    /// The last segment of the argument's type, or the whole type if it's not a path.
+    type_name: String,
+    kind: TokenStream,
+    span: Span,
 }
 
-fn parse_input_type(ty: &syn::Type) -> Result<Input, errors::SubstreamMacroError> {
-    match ty {
-        syn::Type::Path(p) => {
-            let mut input = Input {
-                is_writable_store: false,
-                is_readable_store: false,
-                is_deltas: false,
-                is_string: false,
-                resolved_ty: "".to_owned(),
-                store_type: "".to_string(),
-            };
-            let mut last_type = "".to_owned();
-            for segment in p.path.segments.iter() {
-                last_type = segment.ident.to_string();
-            }
-            input.resolved_ty = last_type.clone();
-            if last_type == "String".to_owned() {
-                input.is_string = true;
-            }
-            for t in WRITABLE_STORE {
-                if last_type == t.to_owned() {
-                    input.is_writable_store = true;
-                    input.store_type = last_type.clone();
-                }
-            }
-            for t in READABLE_STORE {
-                if last_type == t.to_owned() {
-                    input.is_readable_store = true;
-                    input.store_type = last_type.clone();
-                }
-            }
-            if last_type == "Deltas".to_owned() {
-                // todo: should check that it's fully qualified to be our `store::Deltas`
-                input.is_deltas = true;
-            }
-            Ok(input)
+impl Argument {
+    fn new(pat_type: &syn::PatType, name: &syn::Ident, kind: TokenStream) -> Self {
+        let type_name = match &*pat_type.ty {
+            syn::Type::Path(p) => p.path.segments.last().map(|last| last.ident.to_string()),
+            _ => None,
+        };
+
+        Argument {
+            name: name.to_string(),
+            type_name: type_name
+                .unwrap_or_else(|| pat_type.ty.to_token_stream().to_string().replace(' ', "")),
+            kind,
+            span: pat_type.span(),
         }
-        _ => Err(errors::SubstreamMacroError::UnknownInputType(
-            "unable to parse input type".to_owned(),
-        )),
     }
 }
 
@@ -253,98 +205,107 @@ enum OutputType {
 }
 
 const MAP_WRONG_TYPE_ERR: &str = "Module of type Map should return a 'Result<T, Error>', 'Result<Option<T>, Error>', 'Option<T>' or 'T' where 'T' is your output type";
+const INDEX_WRONG_TYPE_ERR: &str = "Module of type Index should return a 'Result<T, Error>', 'Result<Option<T>, Error>', 'Option<T>' or 'T' where 'T' converts into 'substreams::index::Keys'";
+const STORE_WRONG_TYPE_ERR: &str =
+    "Module of type Store should either not have a return statement or return a 'Result<(), Error>'";
 
 fn parse_func_output(
     final_config: &FinalConfiguration,
-    output: syn::ReturnType,
+    sig: &syn::Signature,
 ) -> Result<OutputType, syn::Error> {
-    match final_config.module_type {
-        ModuleType::Map => {
-            if output == syn::ReturnType::Default {
-                return Err(syn::Error::new(Span::call_site(), MAP_WRONG_TYPE_ERR));
-            }
+    let output = &sig.output;
+    let tokens = output
+        .into_token_stream()
+        .into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    let tokens: Vec<&str> = tokens.iter().map(|x| x.as_str()).collect::<Vec<_>>();
 
-            let tokens = output
-                .into_token_stream()
-                .into_iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>();
-            let tokens: Vec<&str> = tokens.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+    match final_config.module_type {
+        ModuleType::Map | ModuleType::Index => {
+            let wrong_type_err = if final_config.module_type == ModuleType::Index {
+                INDEX_WRONG_TYPE_ERR
+            } else {
+                MAP_WRONG_TYPE_ERR
+            };
 
             match tokens[..] {
+                [] => Err(syn::Error::new(sig.ident.span(), wrong_type_err)),
                 ["-", ">", "Result", "<", "Option", "<", ..] => Ok(OutputType::ResultOption),
                 ["-", ">", "Result", "<", ..] => Ok(OutputType::Result),
                 ["-", ">", "Option", "<", ..] => Ok(OutputType::Option),
                 ["-", ">", ..] => Ok(OutputType::Value),
-                [] => Ok(OutputType::Void),
-                _ => Err(syn::Error::new(Span::call_site(), MAP_WRONG_TYPE_ERR)),
+                _ => Err(syn::Error::new_spanned(output, wrong_type_err)),
             }
         }
-        ModuleType::Store => match output {
-            syn::ReturnType::Default => Ok(OutputType::Void),
-            _ => Err(syn::Error::new(
-                Span::call_site(),
-                "Module of type Store should not have a return statement",
-            )),
+        ModuleType::Store => match tokens[..] {
+            [] => Ok(OutputType::Void),
+            ["-", ">", "Result", "<", "()", ..] => Ok(OutputType::Result),
+            _ => Err(syn::Error::new_spanned(output, STORE_WRONG_TYPE_ERR)),
         },
     }
 }
 
 fn build_map_handler(
     input: syn::ItemFn,
+    final_config: &FinalConfiguration,
     output_type: OutputType,
     collected_args: Vec<proc_macro2::TokenStream>,
     decodings: Vec<proc_macro2::TokenStream>,
-    read_only_stores: Vec<proc_macro2::TokenStream>,
-    writable_store: proc_macro2::TokenStream,
 ) -> TokenStream {
     let body = &input.block;
-    let header = quote! {
-        #[no_mangle]
-    };
+    let header = export_header(final_config);
     let func_name = input.sig.ident.clone();
     let lambda_return = input.sig.output.clone();
     let lambda = quote! {
         let func = || #lambda_return {
             #(#decodings)*
-            #(#read_only_stores)*
-            #writable_store
             let result = #body;
             result
         };
     };
 
+    // Index handlers may return anything converting into `Keys`, e.g. a `KeysBuilder`
+    let output = |value: proc_macro2::TokenStream| match final_config.module_type {
+        ModuleType::Index => quote! {
+            substreams::output::<substreams::index::Keys>(#value.into());
+        },
+        _ => quote! {
+            substreams::output(#value);
+        },
+    };
+    let output_value = output(quote! { value });
+    let output_result = output(quote! { result });
+    let handler_name = final_config.module_name(&func_name);
+
     let output_handler = match output_type {
         OutputType::Result => {
             quote! {
-                if result.is_err() {
-                    panic!("{:?}", result.unwrap_err())
+                match result {
+                    Ok(value) => { #output_value }
+                    Err(err) => substreams::errors::report_handler_error(#handler_name, err),
                 }
-
-                substreams::output(result.expect("already checked that result is not an error"));
             }
         }
         OutputType::ResultOption => {
             quote! {
-                if result.is_err() {
-                    panic!("{:?}", result.unwrap_err())
-                }
-
-                if let Some(inner) = result.expect("already checked that result is not an error") {
-                    substreams::output(inner);
+                match result {
+                    Ok(Some(value)) => { #output_value }
+                    Ok(None) => {}
+                    Err(err) => substreams::errors::report_handler_error(#handler_name, err),
                 }
             }
         }
         OutputType::Option => {
             quote! {
                 if let Some(value) = result {
-                    substreams::output(value);
+                    #output_value
                 }
             }
         }
         OutputType::Value => {
             quote! {
-                substreams::output(result);
+                #output_result
             }
         }
         OutputType::Void => {
@@ -361,36 +322,325 @@ fn build_map_handler(
             #output_handler
         }
     };
-    result.into()
+    result
 }
 
 fn build_store_handler(
     input: syn::ItemFn,
+    final_config: &FinalConfiguration,
+    output_type: OutputType,
     collected_args: Vec<proc_macro2::TokenStream>,
     decodings: Vec<proc_macro2::TokenStream>,
-    read_only_stores: Vec<proc_macro2::TokenStream>,
-    writable_store: proc_macro2::TokenStream,
 ) -> TokenStream {
     let body = &input.block;
-    let header = quote! {
-        #[no_mangle]
-    };
+    let header = export_header(final_config);
     let func_name = input.sig.ident.clone();
+
+    if output_type == OutputType::Result {
+        let lambda_return = input.sig.output.clone();
+        let handler_name = final_config.module_name(&func_name);
+
+        return quote! {
+            #header
+            pub extern "C" fn #func_name(#(#collected_args),*){
+                substreams::register_panic_hook();
+                let func = || #lambda_return {
+                    #(#decodings)*
+                    let result = #body;
+                    result
+                };
+
+                if let Err(err) = func() {
+                    substreams::errors::report_handler_error(#handler_name, err);
+                }
+            }
+        };
+    }
+
     let result = quote! {
         #header
         pub extern "C" fn #func_name(#(#collected_args),*){
             substreams::register_panic_hook();
             #(#decodings)*
-            #(#read_only_stores)*
-            #writable_store
             let result = #body;
             result
         }
     };
-    result.into()
+    result
+}
+
+/// The exported function name is the module name in the manifest, the handler's name unless
+/// set with the `name` attribute argument. The `()` arguments of inputs the host doesn't pass
+/// are not part of the exported signature.
+fn export_header(final_config: &FinalConfiguration) -> TokenStream {
+    let export = match &final_config.name {
+        Some(name) => quote! { #[export_name = #name] },
+        None => quote! { #[no_mangle] },
+    };
+    quote! {
+        #export
+        #[allow(improper_ctypes_definitions)]
+    }
+}
+
+/// The output type of index modules, whatever the handler returns.
+const INDEX_OUTPUT: &str = "proto:sf.substreams.index.v1.Keys";
+
+/// Checks that the `output` attribute argument matches the handler's return type: the message
+/// named by `proto:<package>.<Message>` must be the returned `T` (of `Result<Option<T>, _>`,
+/// `Option<T>`, ...) and `bytes` a `Vec<u8>`. Index modules always output [INDEX_OUTPUT].
+fn validate_output(
+    final_config: &FinalConfiguration,
+    sig: &syn::Signature,
+) -> Result<(), syn::Error> {
+    let (output, span) = match &final_config.output {
+        Some((output, span)) => (output.as_str(), *span),
+        None => return Ok(()),
+    };
+
+    if final_config.module_type == ModuleType::Index {
+        if output != INDEX_OUTPUT {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "index modules output `{}`, found `output = \"{}\"`",
+                    INDEX_OUTPUT, output
+                ),
+            ));
+        }
+        return Ok(());
+    }
+
+    let returned = match &sig.output {
+        syn::ReturnType::Type(_, ty) => unwrap_output(ty),
+        syn::ReturnType::Default => return Ok(()),
+    };
+    let name = match returned {
+        syn::Type::Path(path) => path.path.segments.last().map(|last| last.ident.to_string()),
+        _ => None,
+    };
+    let (expected, described) = match output.strip_prefix("proto:") {
+        Some(message) => {
+            let message = message.rsplit('.').next().unwrap_or(message);
+            (message, format!("message `{}`", message))
+        }
+        None => ("Vec", "`Vec<u8>`".to_string()),
+    };
+
+    if name.as_deref() != Some(expected) {
+        let mut error = syn::Error::new_spanned(
+            returned,
+            format!(
+                "handler returns `{}` but `output = \"{}\"` declares {}",
+                returned.to_token_stream().to_string().replace(' ', ""),
+                output,
+                described
+            ),
+        );
+        error.combine(syn::Error::new(span, "output declared here"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Returns `T` of a `Result<T, _>` or `Option<T>` return type, recursively.
+fn unwrap_output(ty: &syn::Type) -> &syn::Type {
+    if let syn::Type::Path(path) = ty {
+        if let Some(last) = path.path.segments.last() {
+            if last.ident == "Result" || last.ident == "Option" {
+                if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                        return unwrap_output(inner);
+                    }
+                }
+            }
+        }
+    }
+    ty
+}
+
+/// Returns constant assertions failing compilation when the kinds of the handler's inputs,
+/// told by `substreams::handlers::HandlerInput`, don't fit the handler:
+///
+/// - a store handler has exactly one writable store, none with `writable_store = "none"`,
+/// - the writable store's update policy and value type are the ones declared by the
+///   `update_policy` and `value_type` attribute arguments and by the manifest,
+/// - a store is read through a single readable store input, `_idx` inputs reading the store
+///   named like them without the suffix,
+/// - the inputs, the writable store excluded, are of the kinds of the module's inputs in the
+///   manifest.
+fn input_assertions(
+    arguments: &[Argument],
+    final_config: &FinalConfiguration,
+    manifest: Option<&ModuleDeclaration>,
+    ident: &syn::Ident,
+) -> TokenStream {
+    let module = final_config.module_name(ident);
+    let mut assertions = Vec::new();
+
+    if final_config.module_type == ModuleType::Store {
+        match final_config.no_writable_store {
+            Some(opt_out) => assertions.push(quote_spanned! {opt_out=>
+                const _: () = assert!(
+                    substreams::handlers::writable_stores(&KINDS) == 0,
+                    "writable store declared on a handler set with `writable_store = \"none\"`"
+                );
+            }),
+            None => assertions.push(quote_spanned! {ident.span()=>
+                const _: () = assert!(
+                    substreams::handlers::writable_stores(&KINDS) > 0,
+                    "store handlers require exactly one writable store, found none. Add a writable store input like `store: StoreSetProto<T>`, or set `writable_store = \"none\"` if the handler doesn't write to its store"
+                );
+                const _: () = assert!(
+                    substreams::handlers::writable_stores(&KINDS) < 2,
+                    "store handlers require exactly one writable store, found more than one"
+                );
+            }),
+        }
+    }
+
+    let in_manifest = format!(" (module `{}` in the manifest)", module);
+    let mut declarations = Vec::new();
+    if let Some((policy, span)) = &final_config.update_policy {
+        declarations.push((Some(*policy), None, *span, String::new()));
+    }
+    if let Some((value_type, span)) = &final_config.value_type {
+        declarations.push((None, Some(value_type), *span, String::new()));
+    }
+    if let Some(manifest) = manifest {
+        declarations.push((
+            manifest.update_policy,
+            manifest.value_type.as_ref(),
+            ident.span(),
+            in_manifest,
+        ));
+    }
+    for (index, argument) in arguments.iter().enumerate() {
+        for (policy, value_type, span, origin) in &declarations {
+            if let Some(policy) = policy {
+                let policy = policy.as_str();
+                let message = format!(
+                    "update policy `{}` does not match the update policy of writable store `{}`{}",
+                    policy, argument.type_name, origin
+                );
+                assertions.push(quote_spanned! {*span=>
+                    const _: () = assert!(
+                        substreams::handlers::update_policy_matches(KINDS[#index], #policy),
+                        #message
+                    );
+                });
+            }
+            if let Some(value_type) = value_type {
+                let kind = value_type.kind();
+                let message = format!(
+                    "value type `{}` does not match the value types of writable store `{}`{}",
+                    value_type.as_str(),
+                    argument.type_name,
+                    origin
+                );
+                assertions.push(quote_spanned! {*span=>
+                    const _: () = assert!(
+                        substreams::handlers::value_type_matches(KINDS[#index], #kind),
+                        #message
+                    );
+                });
+            }
+        }
+    }
+
+    for (second, argument) in arguments.iter().enumerate() {
+        let name = store_name(&argument.name);
+        if let Some(first) = arguments[..second]
+            .iter()
+            .position(|first| store_name(&first.name) == name)
+        {
+            let message = format!(
+                "store `{}` is read more than once, declare a single readable store input for it",
+                name
+            );
+            assertions.push(quote_spanned! {argument.span=>
+                const _: () = assert!(
+                    !(substreams::handlers::is_readable_store(KINDS[#first])
+                        && substreams::handlers::is_readable_store(KINDS[#second])),
+                    #message
+                );
+            });
+        }
+    }
+
+    if let Some(manifest) = manifest {
+        let count = manifest.inputs.len();
+        let message = format!(
+            "handler inputs, its writable store excluded, differ from the {} inputs module `{}` declares in the manifest",
+            count, module
+        );
+        assertions.push(quote_spanned! {ident.span()=>
+            const _: () = assert!(
+                KINDS.len() - substreams::handlers::writable_stores(&KINDS) == #count,
+                #message
+            );
+        });
+
+        // An input is matched against the module input at its position, writable stores
+        // before it excluded, which is at most its own
+        for (index, argument) in arguments.iter().enumerate() {
+            for (position, declared) in manifest.inputs.iter().enumerate().take(index + 1) {
+                let kind = format_ident!("{}", declared.kind.variant());
+                let message = format!(
+                    "input `{}` must be {} to match input #{} ({}) of module `{}` in the manifest",
+                    argument.name,
+                    declared.kind.describe(),
+                    position + 1,
+                    declared.description,
+                    module
+                );
+                assertions.push(quote_spanned! {argument.span=>
+                    const _: () = assert!(
+                        substreams::handlers::manifest_input_index(&KINDS, #index) != #position
+                            || substreams::handlers::input_kind_matches(
+                                KINDS[#index],
+                                substreams::handlers::InputKind::#kind
+                            ),
+                        #message
+                    );
+                });
+            }
+        }
+    }
+
+    if assertions.is_empty() {
+        return TokenStream::new();
+    }
+
+    let count = arguments.len();
+    let kinds = arguments.iter().map(|argument| &argument.kind);
+    quote! {
+        const _: () = {
+            const KINDS: [substreams::handlers::InputKind; #count] = [#(#kinds),*];
+            #(#assertions)*
+        };
+    }
+}
+
+/// Removes the `#[params]` argument attributes, which only mean something to the handler
+/// macros, so the function re-emitted next to a compile error doesn't produce unrelated errors
+/// about them.
+fn strip_params_attributes(item: TokenStream) -> TokenStream {
+    let mut func = match syn::parse2::<syn::ItemFn>(item.clone()) {
+        Ok(func) => func,
+        Err(_) => return item,
+    };
+
+    for arg in func.sig.inputs.iter_mut() {
+        if let syn::FnArg::Typed(pat_type) = arg {
+            pat_type.attrs.retain(|attr| !attr.path.is_ident("params"));
+        }
+    }
+    func.into_token_stream()
 }
 
 fn token_stream_with_error(mut tokens: TokenStream, error: syn::Error) -> TokenStream {
-    tokens.extend(TokenStream::from(error.into_compile_error()));
+    tokens.extend(error.into_compile_error());
     tokens
 }
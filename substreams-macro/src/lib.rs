@@ -2,18 +2,29 @@ use proc_macro::TokenStream;
 
 mod assertions;
 mod config;
-mod errors;
 mod handler;
+mod manifest;
 mod store;
+mod store_key;
 
 #[proc_macro_attribute]
-pub fn map(_args: TokenStream, item: TokenStream) -> TokenStream {
-    handler::main(item.into(), config::ModuleType::Map).into()
+pub fn map(args: TokenStream, item: TokenStream) -> TokenStream {
+    handler::main(args.into(), item.into(), config::ModuleType::Map).into()
 }
 
 #[proc_macro_attribute]
-pub fn store(_args: TokenStream, item: TokenStream) -> TokenStream {
-    handler::main(item.into(), config::ModuleType::Store).into()
+pub fn store(args: TokenStream, item: TokenStream) -> TokenStream {
+    handler::main(args.into(), item.into(), config::ModuleType::Store).into()
+}
+
+#[proc_macro_attribute]
+pub fn index(args: TokenStream, item: TokenStream) -> TokenStream {
+    handler::main(args.into(), item.into(), config::ModuleType::Index).into()
+}
+
+#[proc_macro_derive(StoreKey, attributes(store_key))]
+pub fn derive_store_key(input: TokenStream) -> TokenStream {
+    store_key::main(syn::parse_macro_input!(input as syn::DeriveInput)).into()
 }
 
 // todo: remove this once satisfied with implementation of StoreDelete
@@ -36,14 +47,15 @@ mod test {
         };
 
         assert_ast_eq(
-            main(item, ModuleType::Map).into(),
+            main(quote! {}, item, ModuleType::Map),
             quote! {
                 #[no_mangle]
-                pub extern "C" fn map_transfers(blk_ptr: *mut u8, blk_len: usize) {
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
                     substreams::register_panic_hook();
                     let func = || -> pb::Custom {
-                        let blk: eth::Block = substreams::proto::decode_ptr(blk_ptr, blk_len)
-                            .unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", blk_len, stringify!(eth::Block)));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_transfers", err));
                         let result = {
                             unimplemented!("do something");
                         };
@@ -65,14 +77,15 @@ mod test {
         };
 
         assert_ast_eq(
-            main(item, ModuleType::Map).into(),
+            main(quote! {}, item, ModuleType::Map),
             quote! {
                 #[no_mangle]
-                pub extern "C" fn map_transfers(blk_ptr: *mut u8, blk_len: usize) {
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
                     substreams::register_panic_hook();
                     let func = || -> pb::Custom {
-                        let mut blk: eth::Block = substreams::proto::decode_ptr(blk_ptr, blk_len)
-                            .unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", blk_len, stringify!(eth::Block)));
+                        let mut blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_transfers", err));
                         let result = {
                             unimplemented!("do something");
                         };
@@ -94,14 +107,15 @@ mod test {
         };
 
         assert_ast_eq(
-            main(item, ModuleType::Map).into(),
+            main(quote! {}, item, ModuleType::Map),
             quote! {
                 #[no_mangle]
-                pub extern "C" fn map_transfers(blk_ptr: *mut u8, blk_len: usize) {
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
                     substreams::register_panic_hook();
                     let func = || -> Option<pb::Custom> {
-                        let blk: eth::Block = substreams::proto::decode_ptr(blk_ptr, blk_len)
-                            .unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", blk_len, stringify!(eth::Block)));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_transfers", err));
                         let result = { unimplemented!("do something"); };
                         result
                     };
@@ -124,23 +138,24 @@ mod test {
         };
 
         assert_ast_eq(
-            main(item, ModuleType::Map).into(),
+            main(quote! {}, item, ModuleType::Map),
             quote! {
                 #[no_mangle]
-                pub extern "C" fn map_transfers(blk_ptr: *mut u8, blk_len: usize) {
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
                     substreams::register_panic_hook();
                     let func = || -> Result<pb::Custom> {
-                        let blk: eth::Block = substreams::proto::decode_ptr(blk_ptr, blk_len)
-                            .unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", blk_len, stringify!(eth::Block)));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_transfers", err));
                         let result = { unimplemented!("do something"); };
                         result
                     };
 
                     let result = func();
-                    if result.is_err() {
-                        panic!("{:?}", result.unwrap_err())
+                    match result {
+                        Ok(value) => { substreams::output(value); }
+                        Err(err) => substreams::errors::report_handler_error("map_transfers", err),
                     }
-                    substreams::output(result.expect("already checked that result is not an error"));
                 }
             },
         );
@@ -155,27 +170,536 @@ mod test {
         };
 
         assert_ast_eq(
-            main(item, ModuleType::Map).into(),
+            main(quote! {}, item, ModuleType::Map),
             quote! {
                 #[no_mangle]
-                pub extern "C" fn map_transfers(blk_ptr: *mut u8, blk_len: usize) {
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
                     substreams::register_panic_hook();
                     let func = || -> Result<Option<pb::Custom> > {
-                        let blk: eth::Block = substreams::proto::decode_ptr(blk_ptr, blk_len)
-                            .unwrap_or_else(|_| panic!("Unable to decode Protobuf data ({} bytes) to '{}' message's struct", blk_len, stringify!(eth::Block)));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_transfers", err));
                         let result = { unimplemented!("do something"); };
                         result
                     };
 
                     let result = func();
-                    if result.is_err() {
-                        panic!("{:?}", result.unwrap_err())
+                    match result {
+                        Ok(Some(value)) => { substreams::output(value); }
+                        Ok(None) => {}
+                        Err(err) => substreams::errors::report_handler_error("map_transfers", err),
                     }
-                    if let Some(inner) = result.expect("already checked that result is not an error") {
-                        substreams::output(inner);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_index_value() {
+        let item = quote! {
+            fn index_events(blk: eth::Block) -> KeysBuilder {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Index),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn index_events(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> KeysBuilder {
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("index_events", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+
+                    let result = func();
+                    substreams::output::<substreams::index::Keys>(result.into());
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_index_result_option() {
+        let item = quote! {
+            fn index_events(blk: eth::Block) -> Result<Option<Keys>, Error> {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Index),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn index_events(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> Result<Option<Keys>, Error> {
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("index_events", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+
+                    let result = func();
+                    match result {
+                        Ok(Some(value)) => { substreams::output::<substreams::index::Keys>(value.into()); }
+                        Ok(None) => {}
+                        Err(err) => substreams::errors::report_handler_error("index_events", err),
                     }
                 }
             },
         );
     }
+
+    #[test]
+    fn test_store_result() {
+        let item = quote! {
+            fn store_pools(pools: pb::Pools, store: StoreSetProto<pb::Pool>) -> Result<(), Error> {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Store),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn store_pools(
+                    pools_ptr: <pb::Pools as substreams::handlers::HandlerInput>::Ptr,
+                    pools_len: <pb::Pools as substreams::handlers::HandlerInput>::Len,
+                    store_ptr: <StoreSetProto<pb::Pool> as substreams::handlers::HandlerInput>::Ptr,
+                    store_len: <StoreSetProto<pb::Pool> as substreams::handlers::HandlerInput>::Len
+                ) {
+                    substreams::register_panic_hook();
+                    let func = || -> Result<(), Error> {
+                        let pools: pb::Pools = <pb::Pools as substreams::handlers::HandlerInput>::from_host(pools_ptr, pools_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("store_pools", err));
+                        let store: StoreSetProto<pb::Pool> = <StoreSetProto<pb::Pool> as substreams::handlers::HandlerInput>::from_host(store_ptr, store_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("store_pools", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+
+                    if let Err(err) = func() {
+                        substreams::errors::report_handler_error("store_pools", err);
+                    }
+                }
+                const _: () = {
+                    const KINDS: [substreams::handlers::InputKind; 2usize] = [
+                        <pb::Pools as substreams::handlers::HandlerInput>::KIND,
+                        <StoreSetProto<pb::Pool> as substreams::handlers::HandlerInput>::KIND
+                    ];
+                    const _: () = assert!(
+                        substreams::handlers::writable_stores(&KINDS) > 0,
+                        "store handlers require exactly one writable store, found none. Add a writable store input like `store: StoreSetProto<T>`, or set `writable_store = \"none\"` if the handler doesn't write to its store"
+                    );
+                    const _: () = assert!(
+                        substreams::handlers::writable_stores(&KINDS) < 2,
+                        "store handlers require exactly one writable store, found more than one"
+                    );
+                };
+            },
+        );
+    }
+
+    #[test]
+    fn test_store_invalid_return() {
+        let item = quote! {
+            fn store_pools(pools: pb::Pools, store: StoreSetProto<pb::Pool>) -> u64 {
+                unimplemented!("do something");
+            }
+        };
+
+        assert!(main(quote! {}, item, ModuleType::Store)
+            .to_string()
+            .contains("Module of type Store should either not have a return statement"));
+    }
+
+    #[test]
+    fn test_map_params() {
+        let item = quote! {
+            fn map_filtered(#[params] mut filter: Filter, blk: eth::Block) -> pb::Custom {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Map),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_filtered(filter_ptr: *mut u8, filter_len: usize, blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> pb::Custom {
+                        let mut filter: Filter = substreams::params::decode_ptr(filter_ptr, filter_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_filtered", err));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_filtered", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+                    let result = func();
+                    substreams::output(result);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_map_clock() {
+        let item = quote! {
+            fn map_daily(clock: substreams::clock::Clock, blk: eth::Block) -> pb::Custom {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Map),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_daily(clock_ptr: <substreams::clock::Clock as substreams::handlers::HandlerInput>::Ptr, clock_len: <substreams::clock::Clock as substreams::handlers::HandlerInput>::Len, blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> pb::Custom {
+                        let clock: substreams::clock::Clock = <substreams::clock::Clock as substreams::handlers::HandlerInput>::from_host(clock_ptr, clock_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_daily", err));
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_daily", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+                    let result = func();
+                    substreams::output(result);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_map_with_args() {
+        let item = quote! {
+            fn map_transfers(blk: eth::Block) -> pb::Custom {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(
+                quote! { name = "map_erc20_transfers", output = "proto:pb.Custom" },
+                item,
+                ModuleType::Map,
+            ),
+            quote! {
+                #[export_name = "map_erc20_transfers"]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> pb::Custom {
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_erc20_transfers", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+                    let result = func();
+                    substreams::output(result);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_map_named_reports_module_name() {
+        let item = quote! {
+            fn map_transfers(blk: eth::Block) -> Result<pb::Custom> {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(
+                quote! { name = "map_erc20_transfers" },
+                item,
+                ModuleType::Map,
+            ),
+            quote! {
+                #[export_name = "map_erc20_transfers"]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_transfers(blk_ptr: <eth::Block as substreams::handlers::HandlerInput>::Ptr, blk_len: <eth::Block as substreams::handlers::HandlerInput>::Len) {
+                    substreams::register_panic_hook();
+                    let func = || -> Result<pb::Custom> {
+                        let blk: eth::Block = <eth::Block as substreams::handlers::HandlerInput>::from_host(blk_ptr, blk_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_erc20_transfers", err));
+                        let result = { unimplemented!("do something"); };
+                        result
+                    };
+
+                    let result = func();
+                    match result {
+                        Ok(value) => { substreams::output(value); }
+                        Err(err) => substreams::errors::report_handler_error("map_erc20_transfers", err),
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_manifest_tracked() {
+        let path = std::env::temp_dir().join("substreams-macro-test-manifest-tracked.yaml");
+        std::fs::write(
+            &path,
+            "modules:\n  - name: map_x\n    kind: map\n    inputs:\n      - map: map_y\n",
+        )
+        .unwrap();
+        let path = path.display().to_string();
+
+        let output = main(
+            quote! { manifest = #path },
+            quote! { fn map_x(y: pb::Y) -> pb::Custom { unimplemented!() } },
+            ModuleType::Map,
+        )
+        .to_string();
+        let canonical = std::fs::canonicalize(&path).unwrap();
+        assert!(output.contains(&format!(
+            "const _ : & str = include_str ! ({:?})",
+            canonical.display().to_string()
+        )));
+    }
+
+    #[test]
+    fn test_manifest_clock_checked() {
+        let path = std::env::temp_dir().join("substreams-macro-test-manifest-clock.yaml");
+        std::fs::write(
+            &path,
+            "modules:\n  - name: map_x\n    kind: map\n    inputs:\n      - source: sf.substreams.v1.Clock\n",
+        )
+        .unwrap();
+        let path = path.display().to_string();
+
+        // An imported or aliased clock is matched through its `HandlerInput` kind, not its name
+        let output = main(
+            quote! { manifest = #path },
+            quote! { fn map_x(clock: BlockClock) -> pb::Custom { unimplemented!() } },
+            ModuleType::Map,
+        )
+        .to_string();
+        assert!(output.contains("const KINDS : [substreams :: handlers :: InputKind ; 1usize] = [< BlockClock as substreams :: handlers :: HandlerInput > :: KIND]"));
+        assert!(output.contains("substreams :: handlers :: input_kind_matches (KINDS [0usize] , substreams :: handlers :: InputKind :: Proto) , \"input `clock` must be a protobuf message to match input #1 (source `sf.substreams.v1.Clock`) of module `map_x` in the manifest\""));
+    }
+
+    #[test]
+    fn test_manifest_store_checked() {
+        let path = std::env::temp_dir().join("substreams-macro-test-manifest-store.yaml");
+        std::fs::write(
+            &path,
+            "modules:\n  - name: store_x\n    kind: store\n    updatePolicy: add\n    valueType: bigfloat\n    inputs:\n      - map: map_y\n",
+        )
+        .unwrap();
+        let path = path.display().to_string();
+
+        let output = main(
+            quote! { manifest = #path },
+            quote! { fn store_x(y: pb::Y, s: Balances) {} },
+            ModuleType::Store,
+        )
+        .to_string();
+        assert!(output.contains("update_policy_matches (KINDS [1usize] , \"add\") , \"update policy `add` does not match the update policy of writable store `Balances` (module `store_x` in the manifest)\""));
+        assert!(output.contains("value_type_matches (KINDS [1usize] , \"bigdecimal\")"));
+        assert!(output.contains(
+            "KINDS . len () - substreams :: handlers :: writable_stores (& KINDS) == 1usize"
+        ));
+        assert!(output.contains("substreams :: handlers :: manifest_input_index (& KINDS , 1usize) != 0usize || substreams :: handlers :: input_kind_matches (KINDS [1usize] , substreams :: handlers :: InputKind :: Proto) , \"input `s` must be a protobuf message to match input #1 (map `map_y`) of module `store_x` in the manifest\""));
+    }
+
+    #[test]
+    fn test_idx_inputs() {
+        let map = main(
+            quote! {},
+            quote! { fn map_x(prices_idx: u32) -> pb::Custom { unimplemented!() } },
+            ModuleType::Map,
+        )
+        .to_string();
+        assert!(map
+            .contains("prices_idx_ptr : < u32 as substreams :: handlers :: HandlerInput > :: Ptr"));
+
+        let store = main(
+            quote! {},
+            quote! { fn store_x(prices_idx: u32, #[params] limit_idx: String, s: StoreSetInt64) {} },
+            ModuleType::Store,
+        )
+        .to_string();
+        assert!(store.contains("(prices_idx : u32 , limit_idx_ptr : * mut u8"));
+        assert!(store.contains("[substreams :: handlers :: InputKind :: ReadableStore , substreams :: handlers :: InputKind :: Params , < StoreSetInt64 as substreams :: handlers :: HandlerInput > :: KIND]"));
+    }
+
+    #[test]
+    fn test_output_validated() {
+        let map = |args, item| main(args, item, ModuleType::Map).to_string();
+
+        assert!(map(
+            quote! { output = "proto:my.Pools" },
+            quote! { fn map_pools(blk: eth::Block) -> Result<Option<pb::Pools>, Error> { unimplemented!() } }
+        )
+        .starts_with("# [no_mangle]"));
+        assert!(map(
+            quote! { output = "bytes" },
+            quote! { fn map_raw(blk: eth::Block) -> Option<Vec<u8>> { unimplemented!() } }
+        )
+        .starts_with("# [no_mangle]"));
+        assert!(map(
+            quote! { output = "proto:my.Pools" },
+            quote! { fn map_pools(blk: eth::Block) -> Result<pb::Pool, Error> { unimplemented!() } }
+        )
+        .contains("handler returns `pb::Pool` but `output = \\\"proto:my.Pools\\\"` declares message `Pools`"));
+        assert!(map(
+            quote! { output = "bytes" },
+            quote! { fn map_pools(blk: eth::Block) -> pb::Pools { unimplemented!() } }
+        )
+        .contains("declares `Vec<u8>`"));
+        assert!(main(
+            quote! { output = "proto:my.Keys" },
+            quote! { fn index_pools(blk: eth::Block) -> Keys { unimplemented!() } },
+            ModuleType::Index
+        )
+        .to_string()
+        .contains("index modules output `proto:sf.substreams.index.v1.Keys`"));
+    }
+
+    #[test]
+    fn test_store_args_validated() {
+        let item = quote! {
+            fn store_volumes(pools: pb::Pools, store: StoreAddBigInt) {
+                unimplemented!("do something");
+            }
+        };
+
+        assert!(main(
+            quote! { update_policy = "add", value_type = "bigint" },
+            item.clone(),
+            ModuleType::Store
+        )
+        .to_string()
+        .starts_with("# [no_mangle]"));
+        assert!(main(quote! { value_type = "bigint" }, item.clone(), ModuleType::Store)
+            .to_string()
+            .contains("const _ : () = assert ! (substreams :: handlers :: value_type_matches (KINDS [1usize] , \"bigint\") , \"value type `bigint` does not match the value types of writable store `StoreAddBigInt`\") ;"));
+
+        // Aliases are checked through the store's type, not its name
+        let alias = quote! {
+            fn store_volumes(pools: pb::Pools, volumes: Volumes) {
+                unimplemented!("do something");
+            }
+        };
+        assert!(main(quote! { update_policy = "set" }, alias, ModuleType::Store)
+            .to_string()
+            .contains("const _ : () = assert ! (substreams :: handlers :: update_policy_matches (KINDS [1usize] , \"set\") , \"update policy `set` does not match the update policy of writable store `Volumes`\") ;"));
+        assert!(main(
+            quote! { update_policy = "set", writable_store = "none" },
+            item.clone(),
+            ModuleType::Store
+        )
+        .to_string()
+        .contains("`update_policy` and `value_type` require the handler to have a writable store as an input"));
+        assert!(main(quote! { output = "bytes" }, item, ModuleType::Store)
+            .to_string()
+            .contains("Unknown attribute output is specified for a store handler"));
+    }
+
+    #[test]
+    fn test_store_inputs_validated() {
+        let store = |args, item| main(args, item, ModuleType::Store).to_string();
+
+        assert!(store(quote! {}, quote! { fn store_x(clock: Clock) {} })
+            .contains("store handlers require exactly one writable store, found none"));
+        assert!(store(
+            quote! { writable_store = "none" },
+            quote! { fn store_x(clock: Clock) {} }
+        )
+        .starts_with("# [no_mangle]"));
+        assert!(store(
+            quote! { writable_store = "none" },
+            quote! { fn store_x(clock: Clock, s: StoreSetInt64) {} }
+        )
+        .contains("writable store declared on a handler set with"));
+        assert!(store(
+            quote! {},
+            quote! { fn store_x(prices: StoreGetBigInt, prices_idx: u32, s: StoreSetInt64) {} }
+        )
+        .contains("store `prices` is read more than once"));
+        assert!(store(
+            quote! {},
+            quote! { fn store_x(prices: StoreGetBigInt, tokens: StoreGetProto<pb::Token>, s: StoreSetInt64) {} }
+        )
+        .starts_with("# [no_mangle]"));
+    }
+
+    #[test]
+    fn test_map_lazy_deltas() {
+        let item = quote! {
+            fn map_volumes(volumes: substreams::store::LazyDeltas<BigDecimal>) -> pb::Custom {
+                unimplemented!("do something");
+            }
+        };
+
+        assert_ast_eq(
+            main(quote! {}, item, ModuleType::Map),
+            quote! {
+                #[no_mangle]
+                #[allow(improper_ctypes_definitions)]
+                pub extern "C" fn map_volumes(
+                    volumes_ptr: <substreams::store::LazyDeltas<BigDecimal> as substreams::handlers::HandlerInput>::Ptr,
+                    volumes_len: <substreams::store::LazyDeltas<BigDecimal> as substreams::handlers::HandlerInput>::Len
+                ) {
+                    substreams::register_panic_hook();
+                    let func = || -> pb::Custom {
+                        let volumes: substreams::store::LazyDeltas<BigDecimal> = <substreams::store::LazyDeltas<BigDecimal> as substreams::handlers::HandlerInput>::from_host(volumes_ptr, volumes_len)
+                            .unwrap_or_else(|err| substreams::errors::report_handler_error("map_volumes", err));
+                        let result = {
+                            unimplemented!("do something");
+                        };
+                        result
+                    };
+                    let result = func();
+                    substreams::output(result);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_store_aliases() {
+        let item = quote! {
+            fn store_balances(deltas: pb::Deltas, prices: Prices, balances: Balances) {
+                unimplemented!("do something");
+            }
+        };
+
+        let store = main(quote! {}, item, ModuleType::Store).to_string();
+        assert!(store.contains("pub extern \"C\" fn store_balances (deltas_ptr : < pb :: Deltas as substreams :: handlers :: HandlerInput > :: Ptr"));
+        assert!(store.contains("let balances : Balances = < Balances as substreams :: handlers :: HandlerInput > :: from_host (balances_ptr , balances_len)"));
+        assert!(store.contains("const KINDS : [substreams :: handlers :: InputKind ; 3usize] = [< pb :: Deltas as substreams :: handlers :: HandlerInput > :: KIND , < Prices as substreams :: handlers :: HandlerInput > :: KIND , < Balances as substreams :: handlers :: HandlerInput > :: KIND]"));
+    }
+
+    #[test]
+    fn test_handler_errors() {
+        let error = |item, module_type| main(quote! {}, item, module_type).to_string();
+
+        assert!(error(quote! { struct Pools; }, ModuleType::Map)
+            .contains("#[substreams::handlers::map] can only be applied to a function"));
+        assert!(error(
+            quote! { fn store_x(a: StoreSetInt64, b: StoreAddInt64) {} },
+            ModuleType::Store
+        )
+        .contains("store handlers require exactly one writable store, found more than one"));
+        assert!(error(
+            quote! { fn map_x((a, b): (u8, u8)) -> pb::Custom { unimplemented!() } },
+            ModuleType::Map
+        )
+        .contains("handler arguments must be plain identifiers"));
+    }
 }
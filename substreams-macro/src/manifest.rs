@@ -0,0 +1,452 @@
+use crate::config::{FinalConfiguration, ModuleType};
+use proc_macro2::Span;
+use serde::Deserialize;
+use std::path::PathBuf;
+use substreams_handler_spec::suggest::did_you_mean;
+use substreams_handler_spec::{UpdatePolicy, ValueType};
+
+/// Environment variable giving the manifest to validate handlers against when the handler
+/// macro has no `manifest` attribute argument, relative paths being resolved from the crate's
+/// directory.
+pub const MANIFEST_ENV: &str = "SUBSTREAMS_MANIFEST";
+
+/// The subset of a `substreams.yaml` manifest needed to validate handlers.
+#[derive(Deserialize, Debug, Default)]
+pub struct Manifest {
+    #[serde(default)]
+    pub modules: Vec<Module>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Module {
+    pub name: String,
+    pub kind: String,
+    pub update_policy: Option<String>,
+    pub value_type: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<ModuleInput>,
+    pub output: Option<ModuleOutput>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ModuleOutput {
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ModuleInput {
+    pub source: Option<String>,
+    pub map: Option<String>,
+    pub store: Option<String>,
+    pub mode: Option<String>,
+    pub params: Option<String>,
+}
+
+impl ModuleInput {
+    fn kind(&self) -> Option<InputKind> {
+        match (&self.source, &self.map, &self.store, &self.params) {
+            (Some(_), _, _, _) | (_, Some(_), _, _) => Some(InputKind::Proto),
+            (_, _, Some(_), _) => match self.mode.as_deref().unwrap_or("get") {
+                "get" => Some(InputKind::ReadableStore),
+                "deltas" => Some(InputKind::Deltas),
+                _ => None,
+            },
+            (_, _, _, Some(_)) => Some(InputKind::Params),
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match (&self.source, &self.map, &self.store, &self.params) {
+            (Some(source), _, _, _) => format!("source `{}`", source),
+            (_, Some(map), _, _) => format!("map `{}`", map),
+            (_, _, Some(store), _) => format!(
+                "store `{}` in `{}` mode",
+                store,
+                self.mode.as_deref().unwrap_or("get")
+            ),
+            (_, _, _, Some(_)) => "params".to_string(),
+            _ => "an unknown input".to_string(),
+        }
+    }
+}
+
+/// The kind of a module's input in the manifest, a handler's input matching it when its
+/// `substreams::handlers::InputKind` is the variant of the same name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputKind {
+    /// A protobuf message, either from a source or a map module.
+    Proto,
+    Params,
+    ReadableStore,
+    Deltas,
+}
+
+impl InputKind {
+    pub fn variant(&self) -> &'static str {
+        match self {
+            InputKind::Proto => "Proto",
+            InputKind::Params => "Params",
+            InputKind::ReadableStore => "ReadableStore",
+            InputKind::Deltas => "Deltas",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            InputKind::Proto => "a protobuf message",
+            InputKind::Params => "a params input",
+            InputKind::ReadableStore => "a readable store",
+            InputKind::Deltas => "store deltas",
+        }
+    }
+}
+
+/// A module's input in the manifest.
+#[derive(Debug, PartialEq)]
+pub struct DeclaredInput {
+    pub kind: InputKind,
+    /// The input as written in the manifest, like ``store `store_prices` in `get` mode``.
+    pub description: String,
+}
+
+/// The inputs, update policy and value type a module declares in the manifest, the handler's
+/// inputs and writable store being checked against them by the generated code.
+#[derive(Debug, Default, PartialEq)]
+pub struct ModuleDeclaration {
+    pub inputs: Vec<DeclaredInput>,
+    pub update_policy: Option<UpdatePolicy>,
+    pub value_type: Option<ValueType>,
+}
+
+/// Validates the handler against the manifest given by the `manifest` attribute argument or
+/// the [MANIFEST_ENV] environment variable, returning the manifest's canonical path and the
+/// module's declaration, or `None` when there is no manifest.
+pub fn validate_handler(
+    config: &FinalConfiguration,
+    ident: &syn::Ident,
+) -> Result<Option<(PathBuf, ModuleDeclaration)>, syn::Error> {
+    let (path, span) = match &config.manifest {
+        Some((path, span)) => (path.clone(), *span),
+        None => match std::env::var(MANIFEST_ENV) {
+            Ok(path) if !path.is_empty() => (path, ident.span()),
+            _ => return Ok(None),
+        },
+    };
+
+    let mut resolved = PathBuf::from(&path);
+    if resolved.is_relative() {
+        if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
+            resolved = PathBuf::from(dir).join(resolved);
+        }
+    }
+
+    let content = std::fs::read_to_string(&resolved).map_err(|err| {
+        syn::Error::new(
+            span,
+            format!("unable to read manifest `{}`: {}", resolved.display(), err),
+        )
+    })?;
+    let manifest: Manifest = serde_yaml::from_str(&content).map_err(|err| {
+        syn::Error::new(
+            span,
+            format!("unable to parse manifest `{}`: {}", resolved.display(), err),
+        )
+    })?;
+
+    let name = config.module_name(ident);
+    let declaration = validate(
+        &manifest,
+        config.module_type,
+        &name,
+        ident.span(),
+        config
+            .output
+            .as_ref()
+            .map(|(output, span)| (output.as_str(), *span)),
+    )?;
+
+    Ok(Some((
+        std::fs::canonicalize(&resolved).unwrap_or(resolved),
+        declaration,
+    )))
+}
+
+/// Validates a handler exported as module `name` against `manifest`, errors being reported at
+/// `span`, returning the module's declaration.
+pub fn validate(
+    manifest: &Manifest,
+    module_type: ModuleType,
+    name: &str,
+    span: Span,
+    output: Option<(&str, Span)>,
+) -> Result<ModuleDeclaration, syn::Error> {
+    let module = match manifest.modules.iter().find(|module| module.name == name) {
+        Some(module) => module,
+        None => {
+            let names: Vec<&str> = manifest.modules.iter().map(|m| m.name.as_str()).collect();
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "module `{}` is not defined in the manifest, defined modules are: `{}`.{}",
+                    name,
+                    names.join("`, `"),
+                    did_you_mean(name, names.iter().copied())
+                ),
+            ));
+        }
+    };
+
+    let expected_kind = match module_type {
+        ModuleType::Map => "map",
+        ModuleType::Store => "store",
+        ModuleType::Index => "blockIndex",
+    };
+    if module.kind != expected_kind {
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "module `{}` is a `{}` module in the manifest but the handler is a {} handler",
+                name,
+                module.kind,
+                module_type.as_str()
+            ),
+        ));
+    }
+
+    if let (Some((output, output_span)), Some(declared)) = (output, &module.output) {
+        if output != declared.type_name {
+            return Err(syn::Error::new(
+                output_span,
+                format!(
+                    "output `{}` differs from the output type `{}` of module `{}` in the manifest",
+                    output, declared.type_name, name
+                ),
+            ));
+        }
+    }
+
+    let mut inputs = Vec::with_capacity(module.inputs.len());
+    for (index, declared) in module.inputs.iter().enumerate() {
+        let kind = declared.kind().ok_or_else(|| {
+            syn::Error::new(
+                span,
+                format!(
+                    "input #{} of module `{}` in the manifest is {}, the handler can't match it",
+                    index + 1,
+                    name,
+                    declared.describe()
+                ),
+            )
+        })?;
+        inputs.push(DeclaredInput {
+            kind,
+            description: declared.describe(),
+        });
+    }
+
+    // A handler reads a store through a single readable store input
+    let mut read: Vec<(&str, usize)> = Vec::new();
+    for (index, declared) in module.inputs.iter().enumerate() {
+        let store = match (&declared.store, declared.kind()) {
+            (Some(store), Some(InputKind::ReadableStore)) => store.as_str(),
+            _ => continue,
+        };
+        if let Some((_, first)) = read.iter().find(|(read, _)| *read == store) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "inputs #{} and #{} of module `{}` in the manifest both read store `{}` in `get` mode, declare a single readable store input for it",
+                    first + 1,
+                    index + 1,
+                    name,
+                    store
+                ),
+            ));
+        }
+        read.push((store, index));
+    }
+
+    if module_type != ModuleType::Store {
+        return Ok(ModuleDeclaration {
+            inputs,
+            ..Default::default()
+        });
+    }
+
+    let in_manifest =
+        |err: String| syn::Error::new(span, format!("{} (module `{}` in the manifest)", err, name));
+    let update_policy = module
+        .update_policy
+        .as_deref()
+        .map(UpdatePolicy::parse)
+        .transpose()
+        .map_err(in_manifest)?;
+    let value_type = module
+        .value_type
+        .as_deref()
+        .map(ValueType::parse)
+        .transpose()
+        .map_err(in_manifest)?;
+
+    Ok(ModuleDeclaration {
+        inputs,
+        update_policy,
+        value_type,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate, DeclaredInput, InputKind, Manifest, ModuleDeclaration};
+    use crate::config::ModuleType;
+    use proc_macro2::Span;
+    use substreams_handler_spec::{UpdatePolicy, ValueType};
+
+    const MANIFEST: &str = r#"
+specVersion: v0.1.0
+package:
+  name: uniswap
+  version: v0.1.0
+
+modules:
+  - name: map_pools
+    kind: map
+    inputs:
+      - params: string
+      - source: sf.substreams.v1.Clock
+      - source: sf.ethereum.type.v2.Block
+      - store: store_tokens
+    output:
+      type: proto:uniswap.Pools
+
+  - name: store_volumes
+    kind: store
+    updatePolicy: add
+    valueType: bigint
+    inputs:
+      - map: map_pools
+      - store: store_tokens
+        mode: deltas
+"#;
+
+    fn manifest() -> Manifest {
+        serde_yaml::from_str(MANIFEST).unwrap()
+    }
+
+    fn validate_map(name: &str) -> Result<ModuleDeclaration, String> {
+        validate_map_output(name, None)
+    }
+
+    fn validate_map_output(name: &str, output: Option<&str>) -> Result<ModuleDeclaration, String> {
+        validate(
+            &manifest(),
+            ModuleType::Map,
+            name,
+            Span::call_site(),
+            output.map(|output| (output, Span::call_site())),
+        )
+        .map_err(|err| err.to_string())
+    }
+
+    fn input(kind: InputKind, description: &str) -> DeclaredInput {
+        DeclaredInput {
+            kind,
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_map() {
+        use InputKind::*;
+
+        assert_eq!(
+            validate_map("map_pools"),
+            Ok(ModuleDeclaration {
+                inputs: vec![
+                    input(Params, "params"),
+                    input(Proto, "source `sf.substreams.v1.Clock`"),
+                    input(Proto, "source `sf.ethereum.type.v2.Block`"),
+                    input(ReadableStore, "store `store_tokens` in `get` mode"),
+                ],
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            validate_map("map_pool"),
+            Err("module `map_pool` is not defined in the manifest, defined modules are: `map_pools`, `store_volumes`. Did you mean `map_pools`?".to_string())
+        );
+        assert_eq!(
+            validate_map("store_volumes"),
+            Err("module `store_volumes` is a `store` module in the manifest but the handler is a map handler".to_string())
+        );
+
+        assert!(validate_map_output("map_pools", Some("proto:uniswap.Pools")).is_ok());
+        assert_eq!(
+            validate_map_output("map_pools", Some("proto:uniswap.Pool")),
+            Err("output `proto:uniswap.Pool` differs from the output type `proto:uniswap.Pools` of module `map_pools` in the manifest".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_inputs() {
+        let manifest: Manifest = serde_yaml::from_str(
+            r#"
+modules:
+  - name: map_prices
+    kind: map
+    inputs:
+      - store: store_prices
+      - store: store_prices
+        mode: deltas
+      - store: store_prices
+  - name: map_volumes
+    kind: map
+    inputs:
+      - store: store_volumes
+        mode: replay
+"#,
+        )
+        .unwrap();
+        let validate = |name| {
+            validate(&manifest, ModuleType::Map, name, Span::call_site(), None)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            validate("map_prices"),
+            Err("inputs #1 and #3 of module `map_prices` in the manifest both read store `store_prices` in `get` mode, declare a single readable store input for it".to_string())
+        );
+        assert_eq!(
+            validate("map_volumes"),
+            Err("input #1 of module `map_volumes` in the manifest is store `store_volumes` in `replay` mode, the handler can't match it".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_store() {
+        let declaration = validate(
+            &manifest(),
+            ModuleType::Store,
+            "store_volumes",
+            Span::call_site(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            declaration,
+            ModuleDeclaration {
+                inputs: vec![
+                    input(InputKind::Proto, "map `map_pools`"),
+                    input(InputKind::Deltas, "store `store_tokens` in `deltas` mode"),
+                ],
+                update_policy: Some(UpdatePolicy::Add),
+                value_type: Some(ValueType::parse("bigint").unwrap()),
+            }
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+pub(crate) fn main(input: DeriveInput) -> TokenStream {
+    expand(input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// A variant of the derived enum, or the derived struct itself.
+struct KeyVariant {
+    /// `Self::Variant` for enums, `Self` for structs.
+    path: TokenStream,
+    prefix: String,
+    /// Where the prefix is declared, the attribute's value or the variant's name.
+    prefix_span: Span,
+    prefix_const: Ident,
+    delete_prefix_const: Ident,
+    fields: Fields,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variants = match &input.data {
+        Data::Struct(data) => {
+            let (prefix, prefix_span) = prefix_of(&input.attrs, name)?;
+            vec![KeyVariant {
+                path: quote! { Self },
+                prefix,
+                prefix_span,
+                prefix_const: format_ident!("PREFIX"),
+                delete_prefix_const: format_ident!("DELETE_PREFIX"),
+                fields: data.fields.clone(),
+            }]
+        }
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                let upper = to_snake_case(&ident.to_string()).to_uppercase();
+                let (prefix, prefix_span) = prefix_of(&variant.attrs, ident)?;
+
+                Ok(KeyVariant {
+                    path: quote! { Self::#ident },
+                    prefix,
+                    prefix_span,
+                    prefix_const: format_ident!("{}_PREFIX", upper),
+                    delete_prefix_const: format_ident!("{}_DELETE_PREFIX", upper),
+                    fields: variant.fields.clone(),
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "StoreKey cannot be derived for unions",
+            ))
+        }
+    };
+
+    for (i, variant) in variants.iter().enumerate() {
+        if let Some(first) = variants[..i].iter().find(|x| x.prefix == variant.prefix) {
+            let mut error = syn::Error::new(
+                variant.prefix_span,
+                format!(
+                    "store_key prefix `{}` is used by more than one variant, keys could not be decoded back to their variant",
+                    variant.prefix
+                ),
+            );
+            error.combine(syn::Error::new(first.prefix_span, "prefix first used here"));
+            return Err(error);
+        }
+    }
+
+    let consts = variants.iter().map(|variant| {
+        let prefix = &variant.prefix;
+        let delete_prefix = format!("{}:", prefix);
+        let prefix_const = &variant.prefix_const;
+        let delete_prefix_const = &variant.delete_prefix_const;
+        let prefix_doc = format!(
+            "First segment of `{}` keys, usable with `DeltaExt::key_first_segment_eq`.",
+            prefix
+        );
+        let delete_prefix_doc = format!(
+            "Prefix shared by all `{}` keys (separator included), usable with `delete_prefix`.",
+            prefix
+        );
+
+        quote! {
+            #[doc = #prefix_doc]
+            pub const #prefix_const: &'static str = #prefix;
+            #[doc = #delete_prefix_doc]
+            pub const #delete_prefix_const: &'static str = #delete_prefix;
+        }
+    });
+
+    let prefix_arms = variants.iter().map(|variant| {
+        let path = &variant.path;
+        let prefix_const = &variant.prefix_const;
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { #path { .. } },
+            Fields::Unnamed(_) => quote! { #path(..) },
+            Fields::Unit => quote! { #path },
+        };
+
+        quote! { #pattern => Self::#prefix_const, }
+    });
+
+    let to_key_arms = variants.iter().map(|variant| {
+        let path = &variant.path;
+        let prefix_const = &variant.prefix_const;
+        let bindings = bindings(&variant.fields);
+        let pattern = pattern(path, &variant.fields, &bindings);
+
+        quote! {
+            #pattern => substreams::key::Key::new()
+                .push(Self::#prefix_const)
+                #(.push(#bindings))*
+                .to_string(),
+        }
+    });
+
+    let from_key_arms = variants.iter().map(|variant| {
+        let path = &variant.path;
+        let prefix_const = &variant.prefix_const;
+        let bindings = bindings(&variant.fields);
+        let count = bindings.len() + 1;
+        let indexes = 1..count;
+        let pattern = pattern(path, &variant.fields, &bindings);
+
+        quote! {
+            Some(prefix) if prefix == Self::#prefix_const => {
+                if parsed.len() != #count {
+                    return Err(substreams::key::KeyError::SegmentCount {
+                        key: key.to_string(),
+                        expected: #count,
+                        actual: parsed.len(),
+                    });
+                }
+
+                #(let #bindings = parsed.segment(#indexes)?;)*
+                Ok(#pattern)
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#consts)*
+
+            /// Returns the first segment of the key of this value.
+            pub fn prefix(&self) -> &'static str {
+                match self {
+                    #(#prefix_arms)*
+                }
+            }
+
+            /// Encodes this value into its `:` separated store key.
+            pub fn to_key(&self) -> String {
+                match self {
+                    #(#to_key_arms)*
+                }
+            }
+
+            /// Decodes a store key produced by `to_key`.
+            pub fn from_key(key: &str) -> Result<Self, substreams::key::KeyError> {
+                let parsed = substreams::key::Key::parse(key)?;
+
+                match parsed.segments().first().map(String::as_str) {
+                    #(#from_key_arms)*
+                    _ => Err(substreams::key::KeyError::UnknownPrefix {
+                        key: key.to_string(),
+                    }),
+                }
+            }
+        }
+    })
+}
+
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => Ident::new(&format!("field_{}", i), Span::call_site()),
+        })
+        .collect()
+}
+
+fn pattern(path: &TokenStream, fields: &Fields, bindings: &[Ident]) -> TokenStream {
+    match fields {
+        Fields::Named(_) => quote! { #path { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { #path(#(#bindings),*) },
+        Fields::Unit => quote! { #path },
+    }
+}
+
+/// Reads `#[store_key(prefix = "...")]`, defaulting to the snake_case version of `ident`, and
+/// returns it with the span it is declared at.
+fn prefix_of(attrs: &[Attribute], ident: &Ident) -> syn::Result<(String, Span)> {
+    let mut prefix = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("store_key")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected #[store_key(prefix = \"...\")]",
+                ))
+            }
+        };
+
+        for nested in list.nested {
+            let lit = match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("prefix") =>
+                {
+                    name_value.lit
+                }
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown store_key attribute, expected prefix = \"...\"",
+                    ))
+                }
+            };
+
+            match lit {
+                // `:` and `%` are escaped by `Key`, the `PREFIX` constants would no longer
+                // match the first segment of the keys
+                Lit::Str(value) if value.value().contains(|c| c == ':' || c == '%') => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "store_key prefix cannot contain `:` or `%`, which are escaped in keys",
+                    ))
+                }
+                Lit::Str(value) if !value.value().is_empty() => {
+                    prefix = Some((value.value(), value.span()))
+                }
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "store_key prefix must be a non-empty string literal",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(prefix.unwrap_or_else(|| (to_snake_case(&ident.to_string()), ident.span())))
+}
+
+fn to_snake_case(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len() + 4);
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map_or(false, |x| x.is_lowercase());
+
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                out.push('_');
+            }
+        }
+
+        out.extend(c.to_lowercase());
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{main, to_snake_case};
+    use crate::assertions::assert_ast_eq;
+    use quote::quote;
+
+    #[test]
+    fn test_struct_expansion() {
+        let input = syn::parse2(quote! {
+            #[store_key(prefix = "day")]
+            struct DayKey(String, u64);
+        })
+        .unwrap();
+
+        assert_ast_eq(
+            main(input),
+            quote! {
+                impl DayKey {
+                    #[doc = "First segment of `day` keys, usable with `DeltaExt::key_first_segment_eq`."]
+                    pub const PREFIX: &'static str = "day";
+                    #[doc = "Prefix shared by all `day` keys (separator included), usable with `delete_prefix`."]
+                    pub const DELETE_PREFIX: &'static str = "day:";
+
+                    /// Returns the first segment of the key of this value.
+                    pub fn prefix(&self) -> &'static str {
+                        match self {
+                            Self(..) => Self::PREFIX,
+                        }
+                    }
+
+                    /// Encodes this value into its `:` separated store key.
+                    pub fn to_key(&self) -> String {
+                        match self {
+                            Self(field_0, field_1) => substreams::key::Key::new()
+                                .push(Self::PREFIX)
+                                .push(field_0)
+                                .push(field_1)
+                                .to_string(),
+                        }
+                    }
+
+                    /// Decodes a store key produced by `to_key`.
+                    pub fn from_key(key: &str) -> Result<Self, substreams::key::KeyError> {
+                        let parsed = substreams::key::Key::parse(key)?;
+
+                        match parsed.segments().first().map(String::as_str) {
+                            Some(prefix) if prefix == Self::PREFIX => {
+                                if parsed.len() != 3usize {
+                                    return Err(substreams::key::KeyError::SegmentCount {
+                                        key: key.to_string(),
+                                        expected: 3usize,
+                                        actual: parsed.len(),
+                                    });
+                                }
+
+                                let field_0 = parsed.segment(1usize)?;
+                                let field_1 = parsed.segment(2usize)?;
+                                Ok(Self(field_0, field_1))
+                            }
+                            _ => Err(substreams::key::KeyError::UnknownPrefix {
+                                key: key.to_string(),
+                            }),
+                        }
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_union_is_rejected() {
+        let input = syn::parse2(quote! {
+            union Key { a: u64 }
+        })
+        .unwrap();
+
+        assert!(main(input).to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn test_invalid_prefixes() {
+        let error = |input| main(syn::parse2(input).unwrap()).to_string();
+
+        assert!(error(quote! {
+            enum Key {
+                PoolDay(u64),
+                #[store_key(prefix = "pool_day")]
+                Pool(String),
+            }
+        })
+        .contains("store_key prefix `pool_day` is used by more than one variant"));
+        assert!(error(quote! {
+            #[store_key(prefix = "pool:day")]
+            struct Key(u64);
+        })
+        .contains("store_key prefix cannot contain `:` or `%`"));
+    }
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("Pool"), "pool");
+        assert_eq!(to_snake_case("PoolDayData"), "pool_day_data");
+        assert_eq!(to_snake_case("ERC20Token"), "erc20_token");
+        assert_eq!(to_snake_case("Token0"), "token0");
+    }
+}
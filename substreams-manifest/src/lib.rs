@@ -0,0 +1,262 @@
+//! Generates Substreams manifest module definitions from handler signatures.
+//!
+//! Handlers annotated with `#[substreams::handlers::map]`, `#[substreams::handlers::store]` or
+//! `#[substreams::handlers::index]` already encode most of their module definition: the
+//! inputs, the output type and, for stores, the update policy and value type of the writable
+//! store. The [Scanner] extracts a [ModuleDef] per handler from Rust sources, which can then
+//! be emitted as the `modules:` section of a `substreams.yaml` manifest with [to_yaml] or as a
+//! [Modules] skeleton with [to_modules].
+//!
+//! Inputs are derived from the arguments, in order:
+//!
+//! * `Clock` arguments, `substreams::clock::Clock` or `substreams::pb::substreams::Clock`
+//!   imported or aliased, are the `sf.substreams.v1.Clock` source;
+//! * `Block` arguments are the block source set with [Scanner::block_source], the argument's
+//!   Rust type otherwise;
+//! * `#[params]` and `String` arguments are the module's params;
+//! * readable stores (and `_idx` arguments) are stores in `get` mode and `Deltas`/`LazyDeltas`
+//!   are stores in `deltas` mode, named after the argument;
+//! * any other argument is a map module, named after the argument.
+//!
+//! Sources are not compiled, so the kind of an argument is told from its type's path, `use`
+//! items and `type` aliases of the module being resolved: stores, deltas and the clock are
+//! types of the `substreams` crate, with a path starting with `substreams` or a bare name
+//! (glob imports are not resolved), and a type from any other path, like `pb::Deltas`, is a
+//! protobuf message whatever its name.
+//!
+//! Protobuf types are taken from the `output` and `value_type` attribute arguments when given
+//! and from the Rust types otherwise, `pb::Pools` becoming `proto:pb.Pools`, so the generated
+//! definitions are a skeleton to review rather than a finished manifest.
+//!
+//! ```rust
+//! use substreams_manifest::Scanner;
+//!
+//! let modules = Scanner::new()
+//!     .block_source("sf.ethereum.type.v2.Block")
+//!     .scan_source(
+//!         r#"
+//!         #[substreams::handlers::map(output = "proto:uniswap.Pools")]
+//!         fn map_pools(blk: eth::Block) -> Result<pb::Pools, Error> {
+//!             unimplemented!()
+//!         }
+//!
+//!         #[substreams::handlers::store]
+//!         fn store_pools(map_pools: pb::Pools, store: StoreSetProto<pb::Pool>) {}
+//!         "#,
+//!     )
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     substreams_manifest::to_yaml(&modules),
+//!     r#"modules:
+//! - name: map_pools
+//!   kind: map
+//!   inputs:
+//!   - source: sf.ethereum.type.v2.Block
+//!   output:
+//!     type: proto:uniswap.Pools
+//! - name: store_pools
+//!   kind: store
+//!   updatePolicy: set
+//!   valueType: proto:pb.Pool
+//!   inputs:
+//!   - map: map_pools
+//! "#
+//! );
+//! ```
+mod scan;
+mod yaml;
+
+use std::path::PathBuf;
+use substreams::pb::substreams::module::{self, input, kind_store};
+use substreams::pb::substreams::{Module, Modules};
+use thiserror::Error;
+
+pub use scan::Scanner;
+pub use substreams_handler_spec::CLOCK_SOURCE;
+pub use yaml::to_yaml;
+
+/// The output type of block index modules.
+pub const INDEX_OUTPUT: &str = "proto:sf.substreams.index.v1.Keys";
+
+#[derive(Error, Debug)]
+pub enum ScanError {
+    #[error("unable to read {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("unable to parse {path:?}: {source}")]
+    Parse { path: PathBuf, source: syn::Error },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModuleKind {
+    Map,
+    Store,
+    BlockIndex,
+}
+
+impl ModuleKind {
+    /// Returns the kind as written in the manifest.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModuleKind::Map => "map",
+            ModuleKind::Store => "store",
+            ModuleKind::BlockIndex => "blockIndex",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreMode {
+    Get,
+    Deltas,
+}
+
+impl StoreMode {
+    /// Returns the mode as written in the manifest.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StoreMode::Get => "get",
+            StoreMode::Deltas => "deltas",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputDef {
+    Source(String),
+    Map(String),
+    Store { name: String, mode: StoreMode },
+    Params,
+}
+
+/// ModuleDef is the module definition derived from a handler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleDef {
+    pub name: String,
+    pub kind: ModuleKind,
+    pub inputs: Vec<InputDef>,
+    /// The output type of map and block index modules, `None` when it can't be derived.
+    pub output: Option<String>,
+    pub update_policy: Option<String>,
+    pub value_type: Option<String>,
+}
+
+/// Converts the module definitions into a [Modules] skeleton, each module's entrypoint being
+/// its name in the first binary, which is left for the caller to add.
+///
+/// The `sf.substreams.v1` package has no params inputs nor block index modules, params inputs
+/// are skipped and block index modules are emitted as map modules.
+pub fn to_modules(modules: &[ModuleDef]) -> Modules {
+    Modules {
+        modules: modules.iter().map(to_module).collect(),
+        binaries: vec![],
+    }
+}
+
+fn to_module(def: &ModuleDef) -> Module {
+    let inputs = def
+        .inputs
+        .iter()
+        .filter_map(|input| {
+            let input = match input {
+                InputDef::Source(r#type) => input::Input::Source(input::Source {
+                    r#type: r#type.clone(),
+                }),
+                InputDef::Map(name) => input::Input::Map(input::Map {
+                    module_name: name.clone(),
+                }),
+                InputDef::Store { name, mode } => input::Input::Store(input::Store {
+                    module_name: name.clone(),
+                    mode: match mode {
+                        StoreMode::Get => input::store::Mode::Get,
+                        StoreMode::Deltas => input::store::Mode::Deltas,
+                    } as i32,
+                }),
+                InputDef::Params => return None,
+            };
+            Some(module::Input { input: Some(input) })
+        })
+        .collect();
+
+    let kind = match def.kind {
+        ModuleKind::Map | ModuleKind::BlockIndex => module::Kind::KindMap(module::KindMap {
+            output_type: def.output.clone().unwrap_or_default(),
+        }),
+        ModuleKind::Store => module::Kind::KindStore(module::KindStore {
+            update_policy: update_policy(def.update_policy.as_deref()) as i32,
+            value_type: def.value_type.clone().unwrap_or_default(),
+        }),
+    };
+
+    Module {
+        name: def.name.clone(),
+        binary_index: 0,
+        binary_entrypoint: def.name.clone(),
+        inputs,
+        output: def.output.as_ref().map(|r#type| module::Output {
+            r#type: r#type.clone(),
+        }),
+        initial_block: 0,
+        kind: Some(kind),
+    }
+}
+
+fn update_policy(policy: Option<&str>) -> kind_store::UpdatePolicy {
+    match policy {
+        Some("set") => kind_store::UpdatePolicy::Set,
+        Some("set_if_not_exists") => kind_store::UpdatePolicy::SetIfNotExists,
+        Some("add") => kind_store::UpdatePolicy::Add,
+        Some("min") => kind_store::UpdatePolicy::Min,
+        Some("max") => kind_store::UpdatePolicy::Max,
+        Some("append") => kind_store::UpdatePolicy::Append,
+        _ => kind_store::UpdatePolicy::Unset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_modules, InputDef, ModuleDef, ModuleKind, StoreMode};
+    use pretty_assertions::assert_eq;
+    use substreams::pb::substreams::module::{self, input, kind_store};
+
+    #[test]
+    fn modules_skeleton() {
+        let modules = to_modules(&[ModuleDef {
+            name: "store_volumes".to_string(),
+            kind: ModuleKind::Store,
+            inputs: vec![
+                InputDef::Params,
+                InputDef::Store {
+                    name: "store_pools".to_string(),
+                    mode: StoreMode::Deltas,
+                },
+            ],
+            output: None,
+            update_policy: Some("add".to_string()),
+            value_type: Some("bigint".to_string()),
+        }]);
+
+        let module = &modules.modules[0];
+        assert_eq!(module.binary_entrypoint, "store_volumes");
+        assert_eq!(
+            module.inputs,
+            vec![module::Input {
+                input: Some(input::Input::Store(input::Store {
+                    module_name: "store_pools".to_string(),
+                    mode: input::store::Mode::Deltas as i32,
+                })),
+            }]
+        );
+        assert_eq!(module.output, None);
+        assert_eq!(
+            module.kind,
+            Some(module::Kind::KindStore(module::KindStore {
+                update_policy: kind_store::UpdatePolicy::Add as i32,
+                value_type: "bigint".to_string(),
+            }))
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use prost::Message;
+use std::io::Write;
+use std::process::exit;
+use substreams_manifest::{to_modules, to_yaml, Scanner};
+
+const USAGE: &str = "Usage: substreams-manifest [--format yaml|proto] [--block-source <type>] [--output <file>] [<path>...]
+
+Scans the handlers of the given Rust files or directories (`src` by default) and prints the
+`modules:` section of their `substreams.yaml` manifest, or with `--format proto` an encoded
+`sf.substreams.v1.Modules` skeleton.";
+
+fn main() {
+    let mut format = "yaml".to_string();
+    let mut output = None;
+    let mut scanner = Scanner::new();
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("missing value for {}", flag)))
+        };
+        match arg.as_str() {
+            "--format" => format = value("--format"),
+            "--block-source" => scanner = scanner.block_source(&value("--block-source")),
+            "--output" => output = Some(value("--output")),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => fail(&format!("unknown flag {}", arg)),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("src".to_string());
+    }
+
+    let mut modules = Vec::new();
+    for path in &paths {
+        match scanner.scan_path(path) {
+            Ok(found) => modules.extend(found),
+            Err(err) => fail(&err.to_string()),
+        }
+    }
+
+    let content = match format.as_str() {
+        "yaml" => to_yaml(&modules).into_bytes(),
+        "proto" => to_modules(&modules).encode_to_vec(),
+        _ => fail(&format!(
+            "unknown format {}, expected yaml or proto",
+            format
+        )),
+    };
+
+    let written = match output {
+        Some(path) => std::fs::write(path, content),
+        None => std::io::stdout().write_all(&content),
+    };
+    if let Err(err) = written {
+        fail(&format!("unable to write output: {}", err));
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    exit(1)
+}
@@ -0,0 +1,504 @@
+use crate::{InputDef, ModuleDef, ModuleKind, ScanError, StoreMode, CLOCK_SOURCE, INDEX_OUTPUT};
+use std::path::{Path, PathBuf};
+use substreams_handler_spec::{
+    is_store_index, store_name, writable_store_signature, Imports, Input, InputKind,
+};
+
+/// Scanner extracts the module definitions of the handlers found in Rust sources.
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    block_source: Option<String>,
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the source of `Block` inputs, like `sf.ethereum.type.v2.Block`, the Rust type of
+    /// the input being used when unset.
+    pub fn block_source(mut self, source: &str) -> Self {
+        self.block_source = Some(source.to_string());
+        self
+    }
+
+    /// Returns the module definitions of the handlers in `source`, in declaration order,
+    /// handlers of inline modules included.
+    pub fn scan_source(&self, source: &str) -> Result<Vec<ModuleDef>, syn::Error> {
+        let file = syn::parse_file(source)?;
+        let mut modules = Vec::new();
+        self.scan_items(&file.items, &mut modules)?;
+        Ok(modules)
+    }
+
+    /// Returns the module definitions of the handlers in the `.rs` file `path` or, if it's a
+    /// directory, in all its `.rs` files sorted by path, skipping `target` and hidden
+    /// directories.
+    pub fn scan_path<P: AsRef<Path>>(&self, path: P) -> Result<Vec<ModuleDef>, ScanError> {
+        let mut files = Vec::new();
+        collect_files(path.as_ref(), &mut files)?;
+
+        let mut modules = Vec::new();
+        for file in files {
+            let source = std::fs::read_to_string(&file).map_err(|source| ScanError::Io {
+                path: file.clone(),
+                source,
+            })?;
+            let found = self
+                .scan_source(&source)
+                .map_err(|source| ScanError::Parse { path: file, source })?;
+            modules.extend(found);
+        }
+        Ok(modules)
+    }
+
+    fn scan_items(&self, items: &[syn::Item], modules: &mut Vec<ModuleDef>) -> syn::Result<()> {
+        let imports = Imports::from_items(items);
+        for item in items {
+            match item {
+                syn::Item::Fn(func) => {
+                    if let Some(module) = self.scan_fn(func, &imports)? {
+                        modules.push(module);
+                    }
+                }
+                syn::Item::Mod(syn::ItemMod {
+                    content: Some((_, items)),
+                    ..
+                }) => self.scan_items(items, modules)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_fn(&self, func: &syn::ItemFn, imports: &Imports) -> syn::Result<Option<ModuleDef>> {
+        let (kind, attr) = match func
+            .attrs
+            .iter()
+            .find_map(|attr| handler_kind(&attr.path).map(|kind| (kind, attr)))
+        {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        let mut name = func.sig.ident.to_string();
+        let mut output = None;
+        let mut update_policy = None;
+        let mut value_type = None;
+        if let syn::Meta::List(list) = attr.parse_meta()? {
+            for nested in list.nested {
+                if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(value),
+                    ..
+                })) = nested
+                {
+                    let value = Some(value.value());
+                    match path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                        Some("name") => name = value.unwrap_or_default(),
+                        Some("output") => output = value,
+                        Some("update_policy") => update_policy = value,
+                        Some("value_type") => value_type = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let store_handler = kind == ModuleKind::Store;
+        let mut inputs = Vec::new();
+        for (index, arg) in func.sig.inputs.iter().enumerate() {
+            let pat_type = match arg {
+                syn::FnArg::Typed(pat_type) => pat_type,
+                syn::FnArg::Receiver(_) => continue,
+            };
+            let arg_name = match &*pat_type.pat {
+                syn::Pat::Ident(pat) => pat.ident.to_string(),
+                _ => format!("input_{}", index),
+            };
+            let path = match &*pat_type.ty {
+                syn::Type::Path(ty) => Some(&ty.path),
+                _ => None,
+            };
+            let input = Input::parse(pat_type, imports);
+
+            // Raw `u32` store indexes, only passed to store handlers
+            if is_store_index(&pat_type.ty, store_handler, &arg_name) {
+                inputs.push(InputDef::Store {
+                    name: store_name(&arg_name).to_string(),
+                    mode: StoreMode::Get,
+                });
+                continue;
+            }
+
+            if input.kind == InputKind::WritableStore {
+                let signature = writable_store_signature(&input.type_name);
+                let last = path.and_then(|path| path.segments.last());
+                if let (Some((policy, kinds)), Some(last)) = (signature, last) {
+                    update_policy = update_policy.or_else(|| Some(policy.as_str().to_string()));
+                    value_type = value_type.or_else(|| match kinds.first() {
+                        Some(&"proto") => first_type_argument(last).and_then(proto_type),
+                        kind => kind.map(|kind| kind.to_string()),
+                    });
+                }
+                continue;
+            }
+
+            let input = match input.kind {
+                InputKind::Params => InputDef::Params,
+                InputKind::ReadableStore => InputDef::Store {
+                    name: store_name(&arg_name).to_string(),
+                    mode: StoreMode::Get,
+                },
+                InputKind::Deltas => InputDef::Store {
+                    name: arg_name,
+                    mode: StoreMode::Deltas,
+                },
+                InputKind::Clock => InputDef::Source(CLOCK_SOURCE.to_string()),
+                _ if input.type_name == "Block" => InputDef::Source(
+                    self.block_source
+                        .clone()
+                        .or_else(|| path.map(path_name))
+                        .unwrap_or_default(),
+                ),
+                _ => InputDef::Map(arg_name),
+            };
+            inputs.push(input);
+        }
+
+        let output = match kind {
+            ModuleKind::Map => output.or_else(|| match &func.sig.output {
+                syn::ReturnType::Type(_, ty) => proto_type(unwrap_output(ty)),
+                syn::ReturnType::Default => None,
+            }),
+            ModuleKind::BlockIndex => output.or_else(|| Some(INDEX_OUTPUT.to_string())),
+            ModuleKind::Store => None,
+        };
+
+        Ok(Some(ModuleDef {
+            name,
+            kind,
+            inputs,
+            output,
+            update_policy,
+            value_type,
+        }))
+    }
+}
+
+/// Returns the kind of handler macro `path` is, accepting `map`, `handlers::map` and
+/// `substreams::handlers::map` like paths.
+fn handler_kind(path: &syn::Path) -> Option<ModuleKind> {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let (last, parents) = segments.split_last()?;
+    if !parents.is_empty() && parents.last().map(String::as_str) != Some("handlers") {
+        return None;
+    }
+
+    match last.as_str() {
+        "map" => Some(ModuleKind::Map),
+        "store" => Some(ModuleKind::Store),
+        "index" => Some(ModuleKind::BlockIndex),
+        _ => None,
+    }
+}
+
+/// Unwraps the `T` of `Result<T, E>`, `Result<Option<T>, E>` and `Option<T>` outputs.
+fn unwrap_output(ty: &syn::Type) -> &syn::Type {
+    if let syn::Type::Path(path) = ty {
+        if let Some(last) = path.path.segments.last() {
+            if last.ident == "Result" || last.ident == "Option" {
+                if let Some(inner) = first_type_argument(last) {
+                    return unwrap_output(inner);
+                }
+            }
+        }
+    }
+    ty
+}
+
+fn first_type_argument(segment: &syn::PathSegment) -> Option<&syn::Type> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns `bytes` for `Vec<u8>` and `proto:<path>` for a protobuf message type, `None` for
+/// other types.
+fn proto_type(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            if path
+                .path
+                .segments
+                .last()
+                .map_or(false, |last| last.ident == "Vec")
+            {
+                return Some("bytes".to_string());
+            }
+            Some(format!("proto:{}", path_name(&path.path)))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the `.` separated name of `path` without generics nor `crate`, `self` and `super`
+/// segments.
+fn path_name(path: &syn::Path) -> String {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .filter(|ident| ident != "crate" && ident != "self" && ident != "super")
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), ScanError> {
+    let io_err = |source| ScanError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(io_err)?;
+    entries.sort();
+
+    for entry in entries {
+        let file_name = entry
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if entry.is_dir() {
+            if file_name != "target" && !file_name.starts_with('.') {
+                collect_files(&entry, files)?;
+            }
+        } else if file_name.ends_with(".rs") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+    use crate::{InputDef, ModuleDef, ModuleKind, StoreMode, CLOCK_SOURCE, INDEX_OUTPUT};
+    use pretty_assertions::assert_eq;
+
+    const HANDLERS: &str = r#"
+use substreams::handlers::{map, store};
+use substreams::pb::substreams::Clock;
+
+#[map]
+fn map_pools(#[params] filter: Filter, clock: Clock, blk: eth::Block, tokens: StoreGetProto<pb::Token>) -> Result<Option<crate::pb::Pools>, Error> {
+    unimplemented!()
+}
+
+mod nested {
+    #[substreams::handlers::store(name = "volumes", value_type = "bigint")]
+    fn store_volumes(pools: pb::Pools, prices_idx: u32, store_tokens: Deltas<DeltaProto<pb::Token>>, store: StoreAddBigInt) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[substreams::handlers::index]
+fn index_pools(blk: eth::Block) -> KeysBuilder {
+    unimplemented!()
+}
+
+type Prices = StoreGetBigInt;
+type Balances = substreams::store::StoreAddBigInt;
+
+#[store]
+fn store_balances(deltas: pb::Deltas, raw: pb::StoreGetRaw, prices: Prices, balances: Balances) {}
+
+#[map]
+fn map_prices(prices_idx: pb::Prices) -> pb::Prices {
+    unimplemented!()
+}
+
+#[other::map]
+fn not_a_handler(blk: eth::Block) {}
+
+fn helper() {}
+"#;
+
+    #[test]
+    fn scan_clock_inputs() {
+        let modules = Scanner::new()
+            .scan_source(
+                r#"
+use substreams::{clock::Clock as BlockClock, handlers::map};
+
+type MyClock = substreams::pb::substreams::Clock;
+
+#[map]
+fn map_clocks(a: BlockClock, b: substreams::clock::Clock, c: MyClock, d: pb::Clock, e: Clock) -> pb::Out {
+    unimplemented!()
+}
+"#,
+            )
+            .unwrap();
+
+        let clock = InputDef::Source(CLOCK_SOURCE.to_string());
+        assert_eq!(
+            modules[0].inputs,
+            vec![
+                clock.clone(),
+                clock.clone(),
+                clock.clone(),
+                InputDef::Map("d".to_string()),
+                clock,
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_handlers() {
+        let modules = Scanner::new().scan_source(HANDLERS).unwrap();
+
+        assert_eq!(
+            modules,
+            vec![
+                ModuleDef {
+                    name: "map_pools".to_string(),
+                    kind: ModuleKind::Map,
+                    inputs: vec![
+                        InputDef::Params,
+                        InputDef::Source(CLOCK_SOURCE.to_string()),
+                        InputDef::Source("eth.Block".to_string()),
+                        InputDef::Store {
+                            name: "tokens".to_string(),
+                            mode: StoreMode::Get,
+                        },
+                    ],
+                    output: Some("proto:pb.Pools".to_string()),
+                    update_policy: None,
+                    value_type: None,
+                },
+                ModuleDef {
+                    name: "volumes".to_string(),
+                    kind: ModuleKind::Store,
+                    inputs: vec![
+                        InputDef::Map("pools".to_string()),
+                        InputDef::Store {
+                            name: "prices".to_string(),
+                            mode: StoreMode::Get,
+                        },
+                        InputDef::Store {
+                            name: "store_tokens".to_string(),
+                            mode: StoreMode::Deltas,
+                        },
+                    ],
+                    output: None,
+                    update_policy: Some("add".to_string()),
+                    value_type: Some("bigint".to_string()),
+                },
+                ModuleDef {
+                    name: "index_pools".to_string(),
+                    kind: ModuleKind::BlockIndex,
+                    inputs: vec![InputDef::Source("eth.Block".to_string())],
+                    output: Some(INDEX_OUTPUT.to_string()),
+                    update_policy: None,
+                    value_type: None,
+                },
+                ModuleDef {
+                    name: "store_balances".to_string(),
+                    kind: ModuleKind::Store,
+                    inputs: vec![
+                        InputDef::Map("deltas".to_string()),
+                        InputDef::Map("raw".to_string()),
+                        InputDef::Store {
+                            name: "prices".to_string(),
+                            mode: StoreMode::Get,
+                        },
+                    ],
+                    output: None,
+                    update_policy: Some("add".to_string()),
+                    value_type: Some("bigint".to_string()),
+                },
+                ModuleDef {
+                    name: "map_prices".to_string(),
+                    kind: ModuleKind::Map,
+                    inputs: vec![InputDef::Map("prices_idx".to_string())],
+                    output: Some("proto:pb.Prices".to_string()),
+                    update_policy: None,
+                    value_type: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_store_value_types() {
+        let value_type = |store: &str| {
+            let source = format!("#[store] fn store_x(s: {}) {{}}", store);
+            let modules = Scanner::new().scan_source(&source).unwrap();
+            (
+                modules[0].update_policy.clone().unwrap(),
+                modules[0].value_type.clone(),
+            )
+        };
+
+        assert_eq!(
+            value_type("StoreSetInt64"),
+            ("set".to_string(), Some("int64".to_string()))
+        );
+        assert_eq!(
+            value_type("StoreSetIfNotExistsRaw"),
+            ("set_if_not_exists".to_string(), Some("bytes".to_string()))
+        );
+        assert_eq!(
+            value_type("StoreSetIfNotExistsProto<pb::Pool>"),
+            (
+                "set_if_not_exists".to_string(),
+                Some("proto:pb.Pool".to_string())
+            )
+        );
+        assert_eq!(
+            value_type("StoreAppend<String>"),
+            ("append".to_string(), None)
+        );
+        assert_eq!(
+            value_type("StoreSetValue<Price>"),
+            ("set".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn scan_block_source() {
+        let modules = Scanner::new()
+            .block_source("sf.ethereum.type.v2.Block")
+            .scan_source("#[map] fn map_blocks(blk: eth::Block) -> Vec<u8> { vec![] }")
+            .unwrap();
+
+        assert_eq!(
+            modules[0].inputs,
+            vec![InputDef::Source("sf.ethereum.type.v2.Block".to_string())]
+        );
+        assert_eq!(modules[0].output, Some("bytes".to_string()));
+    }
+
+    #[test]
+    fn scan_invalid_source() {
+        assert!(Scanner::new().scan_source("fn broken(").is_err());
+    }
+}
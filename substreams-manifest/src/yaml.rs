@@ -0,0 +1,143 @@
+use crate::{InputDef, ModuleDef};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    modules: Vec<Module<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Module<'a> {
+    name: &'a str,
+    kind: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_policy: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    inputs: Vec<Input<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<Output<'a>>,
+}
+
+#[derive(Serialize, Default)]
+struct Input<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    map: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    r#type: &'a str,
+}
+
+/// Returns the `modules:` section of a `substreams.yaml` manifest defining `modules`, the
+/// `mode` of store inputs being omitted when it's the default `get` mode.
+pub fn to_yaml(modules: &[ModuleDef]) -> String {
+    let manifest = Manifest {
+        modules: modules
+            .iter()
+            .map(|module| Module {
+                name: &module.name,
+                kind: module.kind.as_str(),
+                update_policy: module.update_policy.as_deref(),
+                value_type: module.value_type.as_deref(),
+                inputs: module.inputs.iter().map(to_input).collect(),
+                output: module.output.as_deref().map(|r#type| Output { r#type }),
+            })
+            .collect(),
+    };
+
+    serde_yaml::to_string(&manifest).expect("manifest modules are always serializable")
+}
+
+fn to_input(input: &InputDef) -> Input<'_> {
+    match input {
+        InputDef::Source(r#type) => Input {
+            source: Some(r#type),
+            ..Input::default()
+        },
+        InputDef::Map(name) => Input {
+            map: Some(name),
+            ..Input::default()
+        },
+        InputDef::Store { name, mode } => Input {
+            store: Some(name),
+            mode: Some(mode.as_str()).filter(|mode| *mode != "get"),
+            ..Input::default()
+        },
+        InputDef::Params => Input {
+            params: Some("string"),
+            ..Input::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_yaml;
+    use crate::{InputDef, ModuleDef, ModuleKind, StoreMode, CLOCK_SOURCE, INDEX_OUTPUT};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn yaml_modules() {
+        let modules = vec![
+            ModuleDef {
+                name: "index_events".to_string(),
+                kind: ModuleKind::BlockIndex,
+                inputs: vec![InputDef::Source(CLOCK_SOURCE.to_string())],
+                output: Some(INDEX_OUTPUT.to_string()),
+                update_policy: None,
+                value_type: None,
+            },
+            ModuleDef {
+                name: "store_volumes".to_string(),
+                kind: ModuleKind::Store,
+                inputs: vec![
+                    InputDef::Params,
+                    InputDef::Store {
+                        name: "store_tokens".to_string(),
+                        mode: StoreMode::Get,
+                    },
+                    InputDef::Store {
+                        name: "store_pools".to_string(),
+                        mode: StoreMode::Deltas,
+                    },
+                ],
+                output: None,
+                update_policy: Some("add".to_string()),
+                value_type: Some("bigint".to_string()),
+            },
+        ];
+
+        assert_eq!(
+            to_yaml(&modules),
+            r#"modules:
+- name: index_events
+  kind: blockIndex
+  inputs:
+  - source: sf.substreams.v1.Clock
+  output:
+    type: proto:sf.substreams.index.v1.Keys
+- name: store_volumes
+  kind: store
+  updatePolicy: add
+  valueType: bigint
+  inputs:
+  - params: string
+  - store: store_tokens
+  - store: store_pools
+    mode: deltas
+"#
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Clock helpers for Substreams.
+//!
+//! Handlers can take the block's [Clock] as input, it's a protobuf message built through
+//! [HandlerInput](crate::handlers::HandlerInput) like any other, matching the
+//! `sf.substreams.v1.Clock` source of the manifest. This module adds helpers on [Clock] to
+//! work with the block's time without pulling a date/time crate, typically to bucket values
+//! of time-series stores:
+//!
+//! ```rust
+//! use substreams::clock::Clock;
+//! use substreams::prelude::*;
+//! use substreams::store::StoreAddInt64;
+//!
+//! #[substreams::handlers::store]
+//! fn store_daily_blocks(clock: Clock, store: StoreAddInt64) {
+//!     store.add(0, clock.day_key("blocks"), 1);
+//!
+//!     if clock.is_multiple_of(1000) {
+//!         store.add(0, clock.hour_key("snapshots"), 1);
+//!     }
+//! }
+//! ```
+use std::fmt;
+
+pub use crate::pb::substreams::Clock;
+
+const SECONDS_PER_HOUR: i64 = 3600;
+const SECONDS_PER_DAY: i64 = 86400;
+
+impl Clock {
+    /// Returns the block's timestamp in seconds since the Unix epoch, 0 when unset.
+    pub fn timestamp_seconds(&self) -> i64 {
+        self.timestamp.as_ref().map_or(0, |ts| ts.seconds)
+    }
+
+    /// Returns the UTC date and time of the block's timestamp.
+    pub fn date_time(&self) -> DateTime {
+        DateTime::from_timestamp(self.timestamp_seconds())
+    }
+
+    /// Returns the number of days between the Unix epoch and the block's timestamp.
+    pub fn day_id(&self) -> i64 {
+        self.timestamp_seconds().div_euclid(SECONDS_PER_DAY)
+    }
+
+    /// Returns the number of hours between the Unix epoch and the block's timestamp.
+    pub fn hour_id(&self) -> i64 {
+        self.timestamp_seconds().div_euclid(SECONDS_PER_HOUR)
+    }
+
+    /// Returns the `<prefix>:<day_id>` key of the block's day bucket, `prefix` being kept
+    /// as-is so it can itself be a multi-segment key like `pool:0x01`.
+    pub fn day_key(&self, prefix: &str) -> String {
+        format!("{}:{}", prefix, self.day_id())
+    }
+
+    /// Returns the `<prefix>:<hour_id>` key of the block's hour bucket, `prefix` being kept
+    /// as-is like for [Clock::day_key].
+    pub fn hour_key(&self, prefix: &str) -> String {
+        format!("{}:{}", prefix, self.hour_id())
+    }
+
+    /// Returns true if the block's number is a multiple of `n`, always false when `n` is 0.
+    pub fn is_multiple_of(&self, n: u64) -> bool {
+        n != 0 && self.number % n == 0
+    }
+}
+
+/// DateTime is a UTC date and time broken down in its calendar fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// Converts a timestamp in seconds since the Unix epoch.
+    pub fn from_timestamp(seconds: i64) -> Self {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let in_day = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (in_day / SECONDS_PER_HOUR) as u32,
+            minute: (in_day % SECONDS_PER_HOUR / 60) as u32,
+            second: (in_day % 60) as u32,
+        }
+    }
+}
+
+/// Formats as RFC 3339, e.g. `2015-07-30T15:26:13Z`.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian `(year, month, day)`, see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, DateTime};
+
+    fn clock(number: u64, seconds: i64) -> Clock {
+        Clock {
+            id: "".to_string(),
+            number,
+            timestamp: Some(prost_types::Timestamp { seconds, nanos: 0 }),
+        }
+    }
+
+    #[test]
+    fn clock_helpers() {
+        // Ethereum block 1 000 000 at 2016-02-13T22:54:13Z
+        let clock = clock(1_000_000, 1455404053);
+
+        assert_eq!(clock.timestamp_seconds(), 1455404053);
+        assert_eq!(clock.day_id(), 16844);
+        assert_eq!(clock.hour_id(), 404278);
+        assert_eq!(clock.day_key("volume"), "volume:16844");
+        assert_eq!(clock.hour_key("volume"), "volume:404278");
+        assert_eq!(clock.day_key("pool:0x01"), "pool:0x01:16844");
+        assert_eq!(clock.date_time().to_string(), "2016-02-13T22:54:13Z");
+        assert!(clock.is_multiple_of(1000));
+        assert!(!clock.is_multiple_of(7));
+        assert!(!clock.is_multiple_of(0));
+    }
+
+    #[test]
+    fn clock_without_timestamp() {
+        let clock = Clock::default();
+
+        assert_eq!(clock.timestamp_seconds(), 0);
+        assert_eq!(clock.date_time().to_string(), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn date_time_from_timestamp() {
+        assert_eq!(
+            DateTime::from_timestamp(951782400),
+            DateTime {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 0,
+                minute: 0,
+                second: 0
+            }
+        );
+        assert_eq!(
+            DateTime::from_timestamp(-1).to_string(),
+            "1969-12-31T23:59:59Z"
+        );
+        assert_eq!(
+            DateTime::from_timestamp(4102444799).to_string(),
+            "2099-12-31T23:59:59Z"
+        );
+    }
+}
@@ -38,15 +38,237 @@
 //!     unimplemented!("do something");
 //! }
 //!```
+//!
+//! When a handler returns an `Err`, the generated handler code reports it to the host
+//! through [report_handler_error] as a [HandlerError] carrying the module's name, the
+//! error's [ErrorKind] and the displayed error followed by its causes (each `context`
+//! added along the way) instead of a generic panic message.
+use std::any::Any;
+use std::fmt;
+use std::str::FromStr;
 
 /// Error proxy for Substreams, simply a type alias to [anyhow::Error].
 ///
 /// See module [crate::errors] level documentation for more information.
 pub type Error = anyhow::Error;
 
+/// Stable classification of an error returned by a handler, sent to the host alongside the
+/// error message. The string representation of each kind never changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Any error not classified below.
+    Handler,
+    /// A [crate::store::DeltaError], a store delta could not be decoded.
+    Delta,
+    /// A [crate::key::KeyError], a store key could not be parsed.
+    Key,
+    /// A [crate::expr_parser::ParseError], a key expression could not be parsed.
+    Expression,
+    /// A [crate::params::ParamsError], the module's params could not be decoded.
+    Params,
+}
+
+impl ErrorKind {
+    /// Classifies `err`, looking through the whole chain of an [Error].
+    pub fn of<E: fmt::Debug + 'static>(err: &E) -> ErrorKind {
+        let err = err as &dyn Any;
+
+        if let Some(err) = err.downcast_ref::<Error>() {
+            return err
+                .chain()
+                .map(Self::classify)
+                .find(|kind| *kind != ErrorKind::Handler)
+                .unwrap_or(ErrorKind::Handler);
+        }
+
+        Self::classify(err)
+    }
+
+    fn classify<E: IsType + ?Sized>(err: &E) -> ErrorKind {
+        if err.is_type::<crate::store::DeltaError>() {
+            ErrorKind::Delta
+        } else if err.is_type::<crate::key::KeyError>() {
+            ErrorKind::Key
+        } else if err.is_type::<crate::expr_parser::ParseError>() {
+            ErrorKind::Expression
+        } else if err.is_type::<crate::params::ParamsError>() {
+            ErrorKind::Params
+        } else {
+            ErrorKind::Handler
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Handler => "handler",
+            ErrorKind::Delta => "delta",
+            ErrorKind::Key => "key",
+            ErrorKind::Expression => "expression",
+            ErrorKind::Params => "params",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "handler" => Ok(ErrorKind::Handler),
+            "delta" => Ok(ErrorKind::Delta),
+            "key" => Ok(ErrorKind::Key),
+            "expression" => Ok(ErrorKind::Expression),
+            "params" => Ok(ErrorKind::Params),
+            _ => Err(format!("unknown error kind {}", s)),
+        }
+    }
+}
+
+// Type checks shared by `dyn Any` and `dyn std::error::Error`, which are not convertible
+// into each other
+trait IsType {
+    fn is_type<T: std::error::Error + 'static>(&self) -> bool;
+}
+
+impl IsType for dyn Any {
+    fn is_type<T: std::error::Error + 'static>(&self) -> bool {
+        self.is::<T>()
+    }
+}
+
+impl IsType for dyn std::error::Error + 'static {
+    fn is_type<T: std::error::Error + 'static>(&self) -> bool {
+        self.is::<T>()
+    }
+}
+
+/// HandlerError is the payload reported to the host when a handler returns an error.
+///
+/// It is encoded as a header line `substreams-handler-error kind=<kind> handler=<handler>`
+/// followed by the error message, so hosts can decode the kind and the handler while hosts
+/// displaying the raw message still show a readable error:
+///
+/// ```rust
+/// use substreams::errors::{ErrorKind, HandlerError};
+///
+/// let err = HandlerError::new("map_pools", anyhow::anyhow!("pool not found"));
+/// let encoded = err.encode();
+/// assert_eq!(encoded, "substreams-handler-error kind=handler handler=map_pools\npool not found");
+/// assert_eq!(HandlerError::decode(&encoded), Some(err));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandlerError {
+    pub kind: ErrorKind,
+    /// Name of the module whose handler returned the error, the handler's name unless set
+    /// with `name = "..."`.
+    pub handler: String,
+    /// The error followed by its causes, if any.
+    pub message: String,
+}
+
+const HANDLER_ERROR_HEADER: &str = "substreams-handler-error";
+
+impl HandlerError {
+    /// Builds the error reported for the module `handler`, the message being the displayed
+    /// error followed by its causes, see [std::error::Error::source].
+    pub fn new<E: Into<Error>>(handler: &str, err: E) -> Self {
+        let err = err.into();
+        let mut message = err.to_string();
+        let mut causes = err.chain().skip(1).peekable();
+        if causes.peek().is_some() {
+            message.push_str("\n\nCaused by:");
+            for cause in causes {
+                message.push_str(&format!("\n    {}", cause));
+            }
+        }
+
+        HandlerError {
+            kind: ErrorKind::of(&err),
+            handler: handler.to_string(),
+            message,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!(
+            "{} kind={} handler={}\n{}",
+            HANDLER_ERROR_HEADER, self.kind, self.handler, self.message
+        )
+    }
+
+    /// Decodes a payload built by [HandlerError::encode], `None` if `payload` is not one.
+    pub fn decode(payload: &str) -> Option<Self> {
+        let (header, message) = payload.split_once('\n')?;
+        let mut fields = header.split(' ');
+        if fields.next()? != HANDLER_ERROR_HEADER {
+            return None;
+        }
+
+        let kind = fields.next()?.strip_prefix("kind=")?.parse().ok()?;
+        let handler = fields.next()?.strip_prefix("handler=")?;
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(HandlerError {
+            kind,
+            handler: handler.to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} error in handler {}: {}",
+            self.kind, self.handler, self.message
+        )
+    }
+}
+
+/// Reports an error returned by the handler of the module `handler` to the host and aborts
+/// the module execution.
+///
+/// On `wasm32`, the [HandlerError] is sent encoded (see [HandlerError::encode]) through the
+/// `register_panic` host function, without a location since it would point at generated code.
+/// On other targets, it panics with the displayed [HandlerError].
+pub fn report_handler_error<E: Into<Error>>(handler: &str, err: E) -> ! {
+    let err = HandlerError::new(handler, err);
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let payload = err.encode();
+        unsafe {
+            crate::externs::register_panic(
+                payload.as_ptr(),
+                payload.len() as u32,
+                std::ptr::null(),
+                0,
+                0,
+                0,
+            );
+        }
+
+        std::process::abort()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    panic!("{}", err)
+}
+
 #[cfg(test)]
 mod test {
-    use super::Error;
+    use super::{Error, ErrorKind, HandlerError};
+    use anyhow::Context;
 
     #[test]
     fn test_from_std_error() {
@@ -63,4 +285,66 @@ mod test {
 
         assert_eq!(format!("{:?}", converted), "source");
     }
+
+    #[test]
+    fn test_error_kind() {
+        let key_error = crate::key::KeyError::UnknownPrefix {
+            key: "a:b".to_string(),
+        };
+
+        assert_eq!(ErrorKind::of(&"plain"), ErrorKind::Handler);
+        assert_eq!(ErrorKind::of(&key_error), ErrorKind::Key);
+        assert_eq!(
+            ErrorKind::of(&anyhow::anyhow!("source")),
+            ErrorKind::Handler
+        );
+
+        let wrapped = Err::<(), _>(key_error)
+            .context("decoding pool key")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&wrapped), ErrorKind::Key);
+    }
+
+    #[test]
+    fn test_handler_error_message() {
+        let err = Err::<(), _>(anyhow::anyhow!("source"))
+            .context("outer")
+            .unwrap_err();
+
+        let handler_error = HandlerError::new("map_pools", err);
+        assert_eq!(
+            handler_error.to_string(),
+            "handler error in handler map_pools: outer\n\nCaused by:\n    source"
+        );
+
+        let key_error = crate::key::KeyError::UnknownPrefix {
+            key: "a:b".to_string(),
+        };
+        let encoded = HandlerError::new("store_pools", key_error.clone()).encode();
+        assert_eq!(
+            encoded,
+            "substreams-handler-error kind=key handler=store_pools\nkey a:b does not start with any known prefix"
+        );
+        assert_eq!(
+            HandlerError::decode(&encoded),
+            Some(HandlerError {
+                kind: ErrorKind::Key,
+                handler: "store_pools".to_string(),
+                message: "key a:b does not start with any known prefix".to_string(),
+            })
+        );
+
+        let wrapped = Err::<(), _>(key_error)
+            .context("decoding pool key")
+            .unwrap_err();
+        assert_eq!(
+            HandlerError::new("store_pools", wrapped).message,
+            "decoding pool key\n\nCaused by:\n    key a:b does not start with any known prefix"
+        );
+        assert_eq!(HandlerError::decode("panicked at main.rs"), None);
+        assert_eq!(
+            HandlerError::decode("substreams-handler-error kind=unknown handler=a\nb"),
+            None
+        );
+    }
 }
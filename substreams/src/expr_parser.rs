@@ -1,124 +1,737 @@
-use anyhow::{Context, Error};
-use pest::{iterators::Pair, Parser};
+//! Key expression language used to filter blocks on the keys of an index.
+//!
+//! Key terms are combined with `||`, `&&` (or whitespace), `!`/`-` for negation and
+//! parentheses. Unquoted key terms may contain `*` (any sequence of characters, possibly
+//! empty) and `?` (exactly one character) wildcards, so `contract:0xabc*` matches every
+//! key starting with `contract:0xabc`. Quoted key terms are always matched literally, a
+//! doubled quote standing for the quote itself (`'it''s'`, `''` for the empty key).
+//! Unquoted key terms accept any punctuation not used by the grammar, so keys built with a
+//! custom [KeyFormat](crate::key::KeyFormat) separator (`pool|0x01`, `pool.0x01`) need no quoting.
+//!
+//! ```rust
+//! use substreams::expr_parser::{matches_sorted_keys_in_parsed_expr, SortedKeys};
+//! use substreams::matches_keys_in_parsed_expr;
+//!
+//! let keys = vec!["contract:0xabc1", "event:transfer"];
+//! assert!(matches_keys_in_parsed_expr(&keys, "contract:0xabc* && !event:mint").unwrap());
+//!
+//! // Sort the keys once when evaluating multiple expressions against the same key set
+//! let sorted = SortedKeys::new(&keys);
+//! assert!(matches_sorted_keys_in_parsed_expr(&sorted, "event:tr?nsfer").unwrap());
+//! assert!(!matches_sorted_keys_in_parsed_expr(&sorted, "'contract:0xabc*'").unwrap());
+//! ```
+//!
+//! When the same expression is evaluated many times, typically a block filter received
+//! as module params, parse it once into an [Expr] and evaluate it on each block:
+//!
+//! ```rust
+//! use substreams::expr_parser::Expr;
+//! use substreams::pb::sf::substreams::index::v1::Keys;
+//!
+//! let expr = Expr::parse("event:transfer && !(contract:0xdef || 'contract:0x 1')").unwrap();
+//! assert_eq!(expr.to_string(), "event:transfer && !(contract:0xdef || 'contract:0x 1')");
+//!
+//! let keys = Keys { keys: vec!["event:transfer".to_string(), "contract:0xabc".to_string()] };
+//! assert!(expr.matches_index_keys(&keys));
+//! ```
+use crate::pb::sf::substreams::index::v1::Keys;
+use anyhow::Error;
+use pest::{
+    error::{ErrorVariant, InputLocation, LineColLocation},
+    iterators::Pair,
+    Parser,
+};
 use pest_derive::Parser;
+use std::collections::BTreeSet;
+use std::fmt;
 
 #[derive(Parser)]
 #[grammar = "expr_parser_rule.pest"]
 struct EParser;
 
-fn parsing(input: &str) -> Result<Pair<Rule>, Error> {
-    let pairs = EParser::parse(Rule::expression, input)
-        .context("parsing input based on the expression rule")?;
+fn parsing(input: &str) -> Result<Pair<Rule>, Box<pest::error::Error<Rule>>> {
+    let mut pairs = EParser::parse(Rule::expression, input).map_err(Box::new)?;
 
-    match pairs.into_iter().next() {
-        Some(pair) => Ok(pair),
-        None => Err(anyhow::Error::msg("no pairs found in input"))
+    Ok(pairs
+        .next()
+        .expect("expression rule always produces a pair when successful"))
+}
+
+/// Parses `input` and evaluates it against `keys` by scanning them, without sorting.
+///
+/// When multiple expressions are evaluated against the same key set, sort it once
+/// with [SortedKeys::new] and use [matches_sorted_keys_in_parsed_expr] instead.
+pub fn matches_keys_in_parsed_expr<K: AsRef<str>, I: AsRef<str>>(
+    keys: &[K],
+    input: I,
+) -> Result<bool, Error> {
+    let expr = Expr::parse(input.as_ref())?;
+    Ok(expr.matches(keys))
+}
+
+/// Same as [matches_keys_in_parsed_expr] but against an already sorted key set.
+pub fn matches_sorted_keys_in_parsed_expr<I: AsRef<str>>(
+    keys: &SortedKeys,
+    input: I,
+) -> Result<bool, Error> {
+    let expr = Expr::parse(input.as_ref())?;
+    Ok(expr.matches_sorted(keys))
+}
+
+/// `ParseError` is returned when an expression does not follow the key expression grammar.
+///
+/// It is displayed with the offending line and a caret under the error position:
+///
+/// ```text
+/// invalid key expression at line 1, column 7: expected `&&`, `||`, end of input, `!`, key, quoted key or `(`, found `)213`
+///   |
+/// 1 | test1 )213
+///   |       ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line of the error.
+    pub line: usize,
+    /// 1-based column of the error, in characters.
+    pub column: usize,
+    /// The offending token, `None` when the end of the input was reached.
+    pub found: Option<String>,
+    /// What the grammar expected at this position.
+    pub expected: Vec<String>,
+    source_line: String,
+}
+
+impl ParseError {
+    fn new(input: &str, err: &pest::error::Error<Rule>) -> Self {
+        let (line, column) = match err.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let offset = match err.location {
+            InputLocation::Pos(offset) | InputLocation::Span((offset, _)) => offset,
+        };
+
+        let rest = &input[offset..];
+        let found: String = match rest.chars().next() {
+            Some(c) if c.is_whitespace() => c.to_string(),
+            _ => rest
+                .chars()
+                .take_while(|c| !c.is_whitespace())
+                .take(32)
+                .collect(),
+        };
+
+        let mut expected: Vec<String> = Vec::new();
+        if let ErrorVariant::ParsingError { positives, .. } = &err.variant {
+            for description in positives.iter().flat_map(describe_rule) {
+                if !expected.iter().any(|x| x == description) {
+                    expected.push(description.to_string());
+                }
+            }
+        }
+
+        ParseError {
+            line,
+            column,
+            found: if found.is_empty() { None } else { Some(found) },
+            expected,
+            source_line: input.lines().nth(line - 1).unwrap_or_default().to_string(),
+        }
     }
 }
 
-pub fn matches_keys_in_parsed_expr<K: AsRef<str>, I: AsRef<str>>(keys: &[K], input: I) -> Result<bool, Error> { 
-       let successful_parse = parsing(input.as_ref()).context("parsing expression")?;
-    Ok(apply_rule(successful_parse, keys))
+fn describe_rule(rule: &Rule) -> &'static [&'static str] {
+    match rule {
+        // Only reached after a complete term, where an operator could also follow
+        Rule::EOI => &["`&&`", "`||`", "end of input"],
+        Rule::keyterm => &["key"],
+        Rule::singleQuoteKeyTerm | Rule::doubleQuoteKeyTerm => &["quoted key"],
+        Rule::not => &["`!`"],
+        _ => &["key", "quoted key", "`(`"],
+    }
 }
 
-fn apply_rule<K: AsRef<str>>(pair: Pair<Rule>,  keys: &[K]) -> bool {
-    match pair.as_rule() {
-        Rule::expression => {
-            let inner_pair = pair.into_inner().next().unwrap();
-            return apply_rule(inner_pair, keys);
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid key expression at line {}, column {}",
+            self.line, self.column
+        )?;
+
+        if let Some((last, others)) = self.expected.split_last() {
+            f.write_str(": expected ")?;
+            if !others.is_empty() {
+                write!(f, "{} or ", others.join(", "))?;
+            }
+            f.write_str(last)?;
         }
-        Rule::or => {
-            let mut result = false;
-            for inner_pair in pair.into_inner() {
-                result = result || apply_rule(inner_pair, keys);
+
+        match &self.found {
+            Some(found) => write!(f, ", found `{}`", found)?,
+            None => f.write_str(", found end of input")?,
+        }
+
+        // Keep tabs so the caret lines up with the offending character
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        write!(
+            f,
+            "\n{gutter} |\n{line} | {source}\n{gutter} | {padding}^",
+            gutter = gutter,
+            line = self.line,
+            source = self.source_line,
+            padding = padding,
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Expr is a parsed key expression, see the [module](crate::expr_parser) documentation
+/// for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A key matched exactly.
+    Key(String),
+    /// An unquoted key term containing `*` or `?` wildcards.
+    Pattern(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, ParseError> {
+        let pair = parsing(input).map_err(|err| ParseError::new(input, &err))?;
+
+        Ok(build_expr(pair))
+    }
+
+    /// Evaluates the expression against `keys`, scanning them for each key term. See
+    /// [Expr::matches_sorted] to evaluate against a key set sorted once with [SortedKeys].
+    pub fn matches<K: AsRef<str>>(&self, keys: &[K]) -> bool {
+        self.eval(&|key| keys.iter().any(|x| x.as_ref() == key), &|pattern| {
+            keys.iter().any(|x| glob_match(pattern, x.as_ref()))
+        })
+    }
+
+    pub fn matches_sorted(&self, keys: &SortedKeys) -> bool {
+        self.eval(&|key| keys.contains(key), &|pattern| keys.matches(pattern))
+    }
+
+    fn eval(&self, contains: &dyn Fn(&str) -> bool, matches: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::Key(key) => contains(key),
+            Expr::Pattern(pattern) => matches(pattern),
+            Expr::Not(inner) => !inner.eval(contains, matches),
+            Expr::And(inner) => inner.iter().all(|expr| expr.eval(contains, matches)),
+            Expr::Or(inner) => inner.iter().any(|expr| expr.eval(contains, matches)),
+        }
+    }
+
+    /// Evaluates the expression against the keys of an index.
+    pub fn matches_index_keys(&self, keys: &Keys) -> bool {
+        self.matches_sorted(&SortedKeys::from(keys))
+    }
+
+    // Higher binds tighter, used to decide when parentheses are needed
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(_) => 0,
+            Expr::And(_) => 1,
+            Expr::Not(_) | Expr::Key(_) | Expr::Pattern(_) => 2,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent: u8) -> fmt::Result {
+        if self.precedence() <= parent {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operands, operator) = match self {
+            Expr::Key(key) if is_bare_key(key) => return f.write_str(key),
+            Expr::Key(key) if key.contains('\'') && !key.contains('"') => {
+                return write!(f, "\"{}\"", key)
             }
-            return result;
-        },
-        Rule::and => {
-            let mut result = true;
-            for inner_pair in pair.into_inner() {
-                result = result && apply_rule(inner_pair, keys);
+            Expr::Key(key) => return write!(f, "'{}'", key.replace('\'', "''")),
+            Expr::Pattern(pattern) => return f.write_str(pattern),
+            Expr::Not(inner) => {
+                f.write_str("!")?;
+                return inner.fmt_operand(f, 1);
+            }
+            // Empty operand lists are never parsed, they are displayed as the equivalent
+            // constants built on the empty key
+            Expr::And(operands) if operands.is_empty() => return f.write_str("('' || !'')"),
+            Expr::Or(operands) if operands.is_empty() => return f.write_str("('' && !'')"),
+            Expr::And(operands) => (operands, " && "),
+            Expr::Or(operands) => (operands, " || "),
+        };
+
+        for (i, operand) in operands.iter().enumerate() {
+            if i > 0 {
+                f.write_str(operator)?;
             }
-            return result;
-        },
-        Rule::value => {
-            let inner_pair = pair.into_inner().next().unwrap();
-            return apply_rule(inner_pair, keys);
+            operand.fmt_operand(f, self.precedence())?;
         }
-        Rule::keyterm => {
-            return keys.iter().any(|key| key.as_ref() == pair.as_str());
+
+        Ok(())
+    }
+}
+
+/// Static analysis of expressions. Terms are treated as independent propositions: a
+/// pattern like `a*` is not known to be implied by the key `a`, so the analysis never
+/// reports a tautology or contradiction that does not hold, but may miss some of them.
+///
+/// Normal forms can grow exponentially with the number of operands, they are meant for
+/// user provided filters, not for arbitrarily large expressions. A tautology normalizes to
+/// `'' || !''` and a contradiction to `'' && !''`, the empty key standing for any term.
+///
+/// ```rust
+/// use substreams::expr_parser::Expr;
+///
+/// let expr = Expr::parse("transfer && (token:0xa || token:0xb) && !mint").unwrap();
+/// assert_eq!(
+///     expr.to_dnf().to_string(),
+///     "!mint && token:0xa && transfer || !mint && token:0xb && transfer"
+/// );
+/// assert_eq!(expr.required_keys().keys.into_iter().collect::<Vec<_>>(), vec!["transfer"]);
+/// assert!(Expr::parse("a && !a").unwrap().is_contradiction());
+/// ```
+impl Expr {
+    /// Returns the disjunctive normal form, an `||` of `&&` of possibly negated terms,
+    /// with contradictory and subsumed conjunctions removed.
+    pub fn to_dnf(&self) -> Expr {
+        if self.is_tautology() {
+            return Expr::tautology();
+        }
+
+        normal_form(dnf_clauses(self, false), false)
+    }
+
+    /// Returns the conjunctive normal form, an `&&` of `||` of possibly negated terms,
+    /// with tautological and subsumed disjunctions removed.
+    pub fn to_cnf(&self) -> Expr {
+        if self.is_contradiction() {
+            return Expr::contradiction();
+        }
+
+        // CNF of e is the negation of the DNF of !e
+        let clauses = dnf_clauses(self, true)
+            .into_iter()
+            .map(|clause| clause.into_iter().map(Literal::negate).collect())
+            .collect();
+
+        normal_form(clauses, true)
+    }
+
+    // `'' || !''`, the normal form of a tautology
+    fn tautology() -> Expr {
+        let key = Expr::Key(String::new());
+        Expr::Or(vec![key.clone(), Expr::Not(Box::new(key))])
+    }
+
+    // `'' && !''`, the normal form of a contradiction
+    fn contradiction() -> Expr {
+        let key = Expr::Key(String::new());
+        Expr::And(vec![key.clone(), Expr::Not(Box::new(key))])
+    }
+
+    /// Returns `true` if the expression matches any key set.
+    pub fn is_tautology(&self) -> bool {
+        dnf_clauses(self, true).is_empty()
+    }
+
+    /// Returns `true` if the expression matches no key set.
+    pub fn is_contradiction(&self) -> bool {
+        dnf_clauses(self, false).is_empty()
+    }
+
+    /// Returns every key and pattern term appearing in the expression, negated or not.
+    pub fn referenced_keys(&self) -> BTreeSet<&str> {
+        let mut keys = BTreeSet::new();
+        self.collect_keys(&mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut BTreeSet<&'a str>) {
+        match self {
+            Expr::Key(term) | Expr::Pattern(term) => {
+                keys.insert(term.as_str());
+            }
+            Expr::Not(inner) => inner.collect_keys(keys),
+            Expr::And(operands) | Expr::Or(operands) => {
+                operands.iter().for_each(|expr| expr.collect_keys(keys))
+            }
+        }
+    }
+
+    /// Returns the key and pattern terms that must be present for the expression to
+    /// match: a block whose index lacks any of the keys, or any key matching one of the
+    /// patterns, can be skipped. Empty when the expression is a contradiction.
+    pub fn required_keys(&self) -> RequiredKeys {
+        let mut clauses = dnf_clauses(self, false).into_iter();
+        let first = match clauses.next() {
+            Some(clause) => clause,
+            None => return RequiredKeys::default(),
+        };
+
+        let mut required: BTreeSet<Literal> = first
+            .into_iter()
+            .filter(|literal| !literal.negated)
+            .collect();
+        for clause in clauses {
+            required.retain(|literal| clause.contains(literal));
+        }
+
+        let (patterns, keys): (Vec<Literal>, Vec<Literal>) =
+            required.into_iter().partition(|literal| literal.pattern);
+        RequiredKeys {
+            keys: keys.into_iter().map(|literal| literal.term).collect(),
+            patterns: patterns.into_iter().map(|literal| literal.term).collect(),
         }
-        Rule::singleQuoteKeyTerm => {
-            return keys.iter().any(|key| key.as_ref() == pair.as_str().trim_matches('\''));
+    }
+}
+
+/// Terms required by an expression, see [Expr::required_keys].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequiredKeys {
+    /// Keys that must be present, exactly.
+    pub keys: BTreeSet<String>,
+    /// Wildcard patterns that must each match at least one key.
+    pub patterns: BTreeSet<String>,
+}
+
+// A possibly negated key or pattern term of a normal form
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Literal {
+    term: String,
+    pattern: bool,
+    negated: bool,
+}
+
+impl Literal {
+    fn negate(self) -> Literal {
+        Literal {
+            negated: !self.negated,
+            ..self
         }
-        Rule::doubleQuoteKeyTerm => {
-            return keys.iter().any(|key| key.as_ref() == pair.as_str().trim_matches('"'));
+    }
+
+    fn to_expr(&self) -> Expr {
+        let term = if self.pattern {
+            Expr::Pattern(self.term.clone())
+        } else {
+            Expr::Key(self.term.clone())
+        };
+
+        match self.negated {
+            true => Expr::Not(Box::new(term)),
+            false => term,
         }
-        _ => {panic!("Unexpected rule encountered")}
-    } 
+    }
 }
 
-#[cfg(test)]
-fn expression_to_string(parsing: Pair<Rule>) -> String {
-    let rule = parsing.as_rule();
-    match rule {
-        Rule::expression => {
-            let inner_pair = parsing.into_inner().next().unwrap();
-            return expression_to_string(inner_pair);
+type Clause = BTreeSet<Literal>;
+type ExprNode = fn(Vec<Expr>) -> Expr;
+
+// Conjunctions of the DNF of `expr` (or of `!expr` when `negated`), contradictory and
+// subsumed conjunctions removed, an empty result means `false`
+fn dnf_clauses(expr: &Expr, negated: bool) -> Vec<Clause> {
+    let clauses = match (expr, negated) {
+        (Expr::Key(term), _) | (Expr::Pattern(term), _) => {
+            let literal = Literal {
+                term: term.clone(),
+                pattern: matches!(expr, Expr::Pattern(_)),
+                negated,
+            };
+            vec![Clause::from([literal])]
         }
-        Rule::or => {
-            let mut result = String::new();
-            result.push_str("[");
-            for inner_pair in parsing.into_inner() {
-                result.push_str(&expression_to_string(inner_pair)); 
-                result.push_str("||");
-            }   
-        
-            if result.ends_with("||") {
-            result.truncate(result.len() - 2);
+        (Expr::Not(inner), _) => dnf_clauses(inner, !negated),
+        (Expr::And(operands), false) | (Expr::Or(operands), true) => {
+            let mut product = vec![Clause::new()];
+            for operand in operands {
+                let operand_clauses = dnf_clauses(operand, negated);
+                product = product
+                    .iter()
+                    .flat_map(|left| {
+                        operand_clauses
+                            .iter()
+                            .map(move |right| left.union(right).cloned().collect())
+                    })
+                    .filter(|clause| !is_contradictory(clause))
+                    .collect();
             }
+            product
+        }
+        (Expr::Or(operands), false) | (Expr::And(operands), true) => operands
+            .iter()
+            .flat_map(|operand| dnf_clauses(operand, negated))
+            .collect(),
+    };
 
-            result.push_str("]");
-            return result;
-        },
-        Rule::and => {
-            let mut result = String::new();
-            result.push_str("<");
-            for inner_pair in parsing.into_inner() {
-                result.push_str(&expression_to_string(inner_pair)); 
-                result.push_str("&&");
-            }   
-        
-            if result.ends_with("&&") {
-            result.truncate(result.len() - 2);
+    remove_subsumed(clauses)
+}
+
+fn is_contradictory(clause: &Clause) -> bool {
+    clause
+        .iter()
+        .any(|literal| literal.negated && clause.contains(&literal.clone().negate()))
+}
+
+// Drops duplicated clauses and clauses that are a superset of another one
+fn remove_subsumed(mut clauses: Vec<Clause>) -> Vec<Clause> {
+    clauses.sort_by(|left, right| left.len().cmp(&right.len()).then(left.cmp(right)));
+    clauses.dedup();
+
+    let mut kept: Vec<Clause> = Vec::with_capacity(clauses.len());
+    for clause in clauses {
+        if !kept.iter().any(|smaller| smaller.is_subset(&clause)) {
+            kept.push(clause);
+        }
+    }
+
+    kept
+}
+
+// Builds `||` of `&&` (or `&&` of `||` when `cnf`), collapsing single operands
+fn normal_form(clauses: Vec<Clause>, cnf: bool) -> Expr {
+    let (outer, inner): (ExprNode, ExprNode) = match cnf {
+        true => (Expr::And, Expr::Or),
+        false => (Expr::Or, Expr::And),
+    };
+
+    if clauses.is_empty() {
+        return match cnf {
+            true => Expr::tautology(),
+            false => Expr::contradiction(),
+        };
+    }
+
+    let mut operands: Vec<Expr> = clauses
+        .iter()
+        .map(|clause| {
+            let mut literals: Vec<Expr> = clause.iter().map(Literal::to_expr).collect();
+            match literals.len() {
+                1 => literals.pop().unwrap(),
+                _ => inner(literals),
             }
+        })
+        .collect();
 
-            result.push_str(">");
-            return result;
-        },
-        Rule::value => {
-            let inner_pair = parsing.into_inner().next().unwrap();
-            return expression_to_string(inner_pair);
+    match operands.len() {
+        1 => operands.pop().unwrap(),
+        _ => outer(operands),
+    }
+}
+
+// Keys that can be written unquoted, see `keyterm` in the grammar
+fn is_bare_key(key: &str) -> bool {
+    let is_key_char = |c: char| match c {
+        '(' | ')' | '\'' | '"' | '!' | '-' | '*' | '?' => false,
+        c if c.is_ascii() => c.is_ascii_alphanumeric() || c.is_ascii_punctuation(),
+        c => !c.is_whitespace(),
+    };
+
+    key.starts_with(is_key_char)
+        && key.chars().all(|c| c == '-' || is_key_char(c))
+        && !key.contains("||")
+        && !key.contains("&&")
+}
+
+// Strips the surrounding quotes of a quoted key term and collapses doubled quotes
+fn unquote(term: &str, quote: char) -> String {
+    let inner = &term[1..term.len() - 1];
+    inner.replace(&format!("{}{}", quote, quote), &quote.to_string())
+}
+
+fn build_expr(pair: Pair<Rule>) -> Expr {
+    match pair.as_rule() {
+        Rule::expression | Rule::value => build_expr(pair.into_inner().next().unwrap()),
+        Rule::or | Rule::and => flatten(pair),
+        Rule::not => Expr::Not(Box::new(build_expr(pair.into_inner().next().unwrap()))),
+        Rule::keyterm if pair.as_str().contains(|c| c == '*' || c == '?') => {
+            Expr::Pattern(pair.as_str().to_string())
         }
-        Rule::keyterm => {
-            return parsing.as_str().to_string();
+        Rule::keyterm => Expr::Key(pair.as_str().to_string()),
+        Rule::singleQuoteKeyTerm => Expr::Key(unquote(pair.as_str(), '\'')),
+        Rule::doubleQuoteKeyTerm => Expr::Key(unquote(pair.as_str(), '"')),
+        _ => {
+            panic!("Unexpected rule encountered")
         }
-        Rule::singleQuoteKeyTerm => {
-            return parsing.as_str().trim_matches('\'').to_string();
+    }
+}
+
+// Builds an `and`/`or` node, a single operand is returned as-is and operands of the same
+// kind (coming from parentheses) are merged so the tree is canonical
+fn flatten(pair: Pair<Rule>) -> Expr {
+    let is_or = pair.as_rule() == Rule::or;
+
+    let mut operands = Vec::new();
+    for operand in pair.into_inner().map(build_expr) {
+        match operand {
+            Expr::Or(inner) if is_or => operands.extend(inner),
+            Expr::And(inner) if !is_or => operands.extend(inner),
+            operand => operands.push(operand),
         }
-        Rule::doubleQuoteKeyTerm => {
-            return parsing.as_str().trim_matches('\"').to_string();
+    }
+
+    match operands.len() {
+        1 => operands.pop().unwrap(),
+        _ if is_or => Expr::Or(operands),
+        _ => Expr::And(operands),
+    }
+}
+
+/// SortedKeys is a sorted and deduplicated view over a key set, exact and wildcard key
+/// terms are then resolved by binary search on the literal prefix of the term instead of
+/// scanning every key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortedKeys<'a> {
+    keys: Vec<&'a str>,
+}
+
+impl<'a> SortedKeys<'a> {
+    pub fn new<K: AsRef<str>>(keys: &'a [K]) -> Self {
+        let mut keys: Vec<&str> = keys.iter().map(AsRef::as_ref).collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        SortedKeys { keys }
+    }
+
+    /// Returns `true` if `key` is present, exactly.
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.binary_search(&key).is_ok()
+    }
+
+    /// Returns `true` if any key matches `pattern`, where `*` matches any sequence of
+    /// characters and `?` exactly one character.
+    pub fn matches(&self, pattern: &str) -> bool {
+        let literal_end = match pattern.find(|c| c == '*' || c == '?') {
+            Some(end) => end,
+            None => return self.contains(pattern),
+        };
+
+        let prefix = &pattern[..literal_end];
+        let start = self.keys.partition_point(|key| *key < prefix);
+        let mut candidates = self.keys[start..]
+            .iter()
+            .take_while(|key| key.starts_with(prefix));
+
+        if &pattern[literal_end..] == "*" {
+            return candidates.next().is_some();
         }
-        _ => {panic!("Unexpected rule encountered")}
+
+        candidates.any(|key| glob_match(pattern, key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<'a> From<&'a Keys> for SortedKeys<'a> {
+    fn from(keys: &'a Keys) -> Self {
+        SortedKeys::new(&keys.keys)
     }
 }
 
+// Iterative glob matching with single-star backtracking, linear for a single `*`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen in pattern and the text position it was matched at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+fn expression_to_string(parsing: Pair<Rule>) -> String {
+    match parsing.as_rule() {
+        Rule::expression | Rule::value => {
+            let inner_pair = parsing.into_inner().next().unwrap();
+            expression_to_string(inner_pair)
+        }
+        Rule::or => {
+            let inner: Vec<_> = parsing.into_inner().map(expression_to_string).collect();
+            format!("[{}]", inner.join("||"))
+        }
+        Rule::and => {
+            let inner: Vec<_> = parsing.into_inner().map(expression_to_string).collect();
+            format!("<{}>", inner.join("&&"))
+        }
+        Rule::not => {
+            let inner_pair = parsing.into_inner().next().unwrap();
+            format!("!{}", expression_to_string(inner_pair))
+        }
+        Rule::keyterm => parsing.as_str().to_string(),
+        Rule::singleQuoteKeyTerm => unquote(parsing.as_str(), '\''),
+        Rule::doubleQuoteKeyTerm => unquote(parsing.as_str(), '"'),
+        _ => {
+            panic!("Unexpected rule encountered")
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use rstest::rstest;
     use super::*;
-    static TEST_KEYS: &[&str] = &["test", "test1", "test2", "test3", "test4", "test5", "test 6"];
+    use rstest::rstest;
+    static TEST_KEYS: &[&str] = &[
+        "test", "test1", "test2", "test3", "test4", "test5", "test 6",
+    ];
+    static SEPARATED_KEYS: &[&str] = &["pool:0x01", "pool/0x02", "pool|0x03"];
+    static FAMILY_KEYS: &[&str] = &[
+        "contract:0xabc1",
+        "contract:0xabc2",
+        "contract:0xdef",
+        "event:transfer",
+        "event:approval",
+    ];
 
     #[rstest]
     #[case(TEST_KEYS, "test", true)]
@@ -127,56 +740,370 @@ mod tests {
     #[case(TEST_KEYS, "'test_6' && test3", false)]
     #[case(TEST_KEYS, "\"test 6\" || test7", true)]
     #[case(TEST_KEYS, "\"test 6\" && test3", true)]
-
     #[case(TEST_KEYS, "test1 || test", true)]
     #[case(TEST_KEYS, "test1 || test6", true)]
     #[case(TEST_KEYS, "test6 || test7", false)]
-
     #[case(TEST_KEYS, "test1 || test || test2", true)]
     #[case(TEST_KEYS, "test1 || test6 || test7", true)]
     #[case(TEST_KEYS, "test6 || test7 || test8", false)]
-
     #[case(TEST_KEYS, "test1 && test", true)]
     #[case(TEST_KEYS, "test1 && test6", false)]
     #[case(TEST_KEYS, "test6 && test7", false)]
-
     #[case(TEST_KEYS, "test1 && test && test2", true)]
     #[case(TEST_KEYS, "test1 && test2 && test7", false)]
     #[case(TEST_KEYS, "test6 && test7 && test8", false)]
-
     #[case(TEST_KEYS, "test1 test", true)]
     #[case(TEST_KEYS, "test1 test6", false)]
     #[case(TEST_KEYS, "test6 test7", false)]
-
     #[case(TEST_KEYS, "(test1)", true)]
     #[case(TEST_KEYS, "(test1 test6)", false)]
-
     #[case(TEST_KEYS, "test1     test2 ", true)]
     #[case(TEST_KEYS, "test1    && test2 ", true)]
     #[case(TEST_KEYS, "test1    &&     test6", false)]
     #[case(TEST_KEYS, "(test1   ||  test3)       &&  test6 ", false)]
-    #[case(TEST_KEYS, "(test1  ||     test6 || test7  )     && (test4 || test5) && test3 ", true)]
-
-    #[case(TEST_KEYS, "(test1 || test6 || test7) && (test4 || test5) && test3 ", true)]
-    #[case(TEST_KEYS, "(test1 && test6 && test7) || (test4 && test5) || test3 ", true)]
+    #[case(
+        TEST_KEYS,
+        "(test1  ||     test6 || test7  )     && (test4 || test5) && test3 ",
+        true
+    )]
+    #[case(
+        TEST_KEYS,
+        "(test1 || test6 || test7) && (test4 || test5) && test3 ",
+        true
+    )]
+    #[case(
+        TEST_KEYS,
+        "(test1 && test6 && test7) || (test4 && test5) || test3 ",
+        true
+    )]
+    #[case(TEST_KEYS, "!test6", true)]
+    #[case(TEST_KEYS, "!test1", false)]
+    #[case(TEST_KEYS, "-test6", true)]
+    #[case(TEST_KEYS, "!!test1", true)]
+    #[case(TEST_KEYS, "! test1", false)]
+    #[case(TEST_KEYS, "test1 && !test6", true)]
+    #[case(TEST_KEYS, "test1 && !test2", false)]
+    #[case(TEST_KEYS, "test1 !test6", true)]
+    #[case(TEST_KEYS, "test1 -test2", false)]
+    #[case(TEST_KEYS, "!test6 || test7", true)]
+    #[case(TEST_KEYS, "!test1 || test7", false)]
+    #[case(TEST_KEYS, "!(test1 || test7)", false)]
+    #[case(TEST_KEYS, "!(test6 || test7)", true)]
+    #[case(TEST_KEYS, "!(test1 && test6) && test2", true)]
+    #[case(TEST_KEYS, "!'test 6'", false)]
+    #[case(TEST_KEYS, "!\"test 7\"", true)]
+    #[case(TEST_KEYS, "(test1 || test7) test2", true)]
+    #[case(TEST_KEYS, "'test 6' test7", false)]
+    #[case(FAMILY_KEYS, "contract:0xabc*", true)]
+    #[case(FAMILY_KEYS, "contract:0xab*", true)]
+    #[case(FAMILY_KEYS, "contract:0xabd*", false)]
+    #[case(FAMILY_KEYS, "contract:*", true)]
+    #[case(FAMILY_KEYS, "*", true)]
+    #[case(FAMILY_KEYS, "*:transfer", true)]
+    #[case(FAMILY_KEYS, "*:mint", false)]
+    #[case(FAMILY_KEYS, "contract:0xabc?", true)]
+    #[case(FAMILY_KEYS, "contract:0xab?", false)]
+    #[case(FAMILY_KEYS, "contract:0x??f", true)]
+    #[case(FAMILY_KEYS, "contract:*2 && event:*", true)]
+    #[case(FAMILY_KEYS, "event:*r*l", true)]
+    #[case(FAMILY_KEYS, "event:*a*a", false)]
+    #[case(FAMILY_KEYS, "!contract:0xabc* || event:mint", false)]
+    #[case(FAMILY_KEYS, "'contract:0xabc*'", false)]
+    #[case(SEPARATED_KEYS, "pool:0x01", true)]
+    #[case(SEPARATED_KEYS, "pool/0x02 && pool:0x01", true)]
+    #[case(SEPARATED_KEYS, "pool/0x01", false)]
+    #[case(SEPARATED_KEYS, "'pool|0x03'", true)]
+    #[case(SEPARATED_KEYS, "pool|0x03 && pool/0x02", true)]
+    #[case(SEPARATED_KEYS, "pool.0x04 || pool→0x05 || pool-0x06", false)]
+    #[case(SEPARATED_KEYS, "pool|* && !pool|0x04", true)]
 
-    fn test_matches_keys_in_parsed_expr(#[case] keys: &[&str], #[case] input: &str, #[case] expected: bool) {
+    fn test_matches_keys_in_parsed_expr(
+        #[case] keys: &[&str],
+        #[case] input: &str,
+        #[case] expected: bool,
+    ) {
         let pair = parsing(input).unwrap();
         let expr_as_string = expression_to_string(pair);
 
-        let result = matches_keys_in_parsed_expr(keys, input).expect("matching keys in parsed expression");
-        
+        let result =
+            matches_keys_in_parsed_expr(keys, input).expect("matching keys in parsed expression");
+
         assert_eq!(result, expected, "This expression ast is {expr_as_string}");
+
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(
+            expr.matches(keys),
+            expected,
+            "Compiled expression is {expr}"
+        );
+        assert_eq!(
+            expr.matches_sorted(&SortedKeys::new(keys)),
+            expected,
+            "Compiled expression against sorted keys is {expr}"
+        );
+        assert_eq!(
+            Expr::parse(&expr.to_string()).unwrap(),
+            expr,
+            "Display must round-trip"
+        );
     }
 
     #[rstest]
-    #[case(TEST_KEYS, "test1 *213 ", "parsing expression")]
-    #[case(TEST_KEYS, "|213 test", "parsing expression")]
-    #[case(TEST_KEYS, "", "parsing expression")]
+    #[case(
+        TEST_KEYS,
+        "test1 )213 ",
+        (1, 7, Some(")213")),
+        "expected `&&`, `||`, end of input, `!`, key, quoted key or `(`, found `)213`"
+    )]
+    #[case(
+        TEST_KEYS,
+        "||213 test",
+        (1, 1, Some("||213")),
+        "expected key, quoted key or `(`, found `||213`"
+    )]
+    #[case(
+        TEST_KEYS,
+        "",
+        (1, 1, None),
+        "expected key, quoted key or `(`, found end of input"
+    )]
+    #[case(
+        TEST_KEYS,
+        "test1 && !",
+        (1, 11, None),
+        "expected `!`, key, quoted key or `(`, found end of input"
+    )]
+    #[case(
+        TEST_KEYS,
+        "test1!",
+        (1, 6, Some("!")),
+        "expected `&&`, `||` or end of input, found `!`"
+    )]
+    #[case(
+        TEST_KEYS,
+        "test1 ||\n (test2 && )",
+        (2, 12, Some(")")),
+        "expected `!`, key, quoted key or `(`, found `)`"
+    )]
 
-    fn test_matches_keys_in_parsed_expr_error(#[case] keys: &[&str], #[case] input: &str, #[case] expected_error: &str) {
+    fn test_matches_keys_in_parsed_expr_error(
+        #[case] keys: &[&str],
+        #[case] input: &str,
+        #[case] position: (usize, usize, Option<&str>),
+        #[case] expected_error: &str,
+    ) {
         let result = matches_keys_in_parsed_expr(keys, input).expect_err("parsing is not failing");
-        assert_eq!(result.to_string(), expected_error);
+        let err = result
+            .downcast_ref::<ParseError>()
+            .expect("error is a ParseError");
+
+        assert_eq!(
+            (err.line, err.column, err.found.as_deref()),
+            position,
+            "{}",
+            err
+        );
+        assert_eq!(
+            result.to_string().lines().next().unwrap(),
+            format!(
+                "invalid key expression at line {}, column {}: {}",
+                position.0, position.1, expected_error
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_error_diagram() {
+        let err = Expr::parse("a ||\n\tb c )d").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid key expression at line 2, column 6: expected `&&`, `||`, end of input, `!`, key, quoted key or `(`, found `)d`\n  |\n2 | \tb c )d\n  | \t    ^"
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("a*b*c", "abc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(glob_match("a**", "a"));
+        assert!(glob_match("*aab", "aaaab"));
+        assert!(glob_match("é?", "éà"));
+    }
+
+    #[test]
+    fn test_sorted_keys_from_index_keys() {
+        let index = Keys {
+            keys: vec!["b".to_string(), "a:1".to_string(), "a:1".to_string()],
+        };
+        let keys = SortedKeys::from(&index);
+
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains("a:1"));
+        assert!(keys.matches("a:*"));
+        assert!(!keys.matches("c*"));
+        assert!(matches_sorted_keys_in_parsed_expr(&keys, "a:? && b").unwrap());
+    }
+
+    #[rstest]
+    #[case("a", "a")]
+    #[case("(((a)))", "a")]
+    #[case("a b  &&c", "a && b && c")]
+    #[case("(a && b) && (c d)", "a && b && c && d")]
+    #[case("(a || b) || c", "a || b || c")]
+    #[case("a && b || c", "a && b || c")]
+    #[case("a && (b || c)", "a && (b || c)")]
+    #[case("-(a || b) !c", "!(a || b) && !c")]
+    #[case("!!a", "!!a")]
+    #[case("!(a && b)", "!(a && b)")]
+    #[case("'a b' || \"it's\"", "'a b' || \"it's\"")]
+    #[case("'plain'", "plain")]
+    #[case("contract:0x* && event:?", "contract:0x* && event:?")]
+    #[case("'contract:0x*'", "'contract:0x*'")]
+    #[case("'it''s \"quoted\"'", "'it''s \"quoted\"'")]
+    #[case("\"say \"\"hi\"\"\"", "'say \"hi\"'")]
+    #[case("'' || \"\"", "'' || ''")]
+    fn test_expr_display(#[case] input: &str, #[case] expected: &str) {
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(expr.to_string(), expected);
+        assert_eq!(Expr::parse(expected).unwrap(), expr);
     }
-    
-}
\ No newline at end of file
+
+    #[test]
+    fn test_expr_tree() {
+        assert_eq!(
+            Expr::parse("a && !(b || c*)").unwrap(),
+            Expr::And(vec![
+                Expr::Key("a".to_string()),
+                Expr::Not(Box::new(Expr::Or(vec![
+                    Expr::Key("b".to_string()),
+                    Expr::Pattern("c*".to_string()),
+                ]))),
+            ])
+        );
+        assert!(Expr::parse("a &&").is_err());
+
+        assert_eq!(
+            Expr::parse("'it''s' && ''").unwrap(),
+            Expr::And(vec![
+                Expr::Key("it's".to_string()),
+                Expr::Key("".to_string())
+            ])
+        );
+        let key = Expr::Key("'\"".to_string());
+        assert_eq!(Expr::parse(&key.to_string()).unwrap(), key);
+    }
+
+    #[rstest]
+    #[case("a", "a", "a")]
+    #[case("a b || c", "c || a && b", "(a || c) && (b || c)")]
+    #[case("a && (b || c)", "a && b || a && c", "a && (b || c)")]
+    #[case("!(a || b)", "!a && !b", "!a && !b")]
+    #[case("!(a && b)", "!a || !b", "!a || !b")]
+    #[case("a || a && b", "a", "a")]
+    #[case("(a || b) && (a || c)", "a || b && c", "(a || b) && (a || c)")]
+    #[case("x* && !'y z'", "x* && !'y z'", "x* && !'y z'")]
+    fn test_normal_forms(#[case] input: &str, #[case] dnf: &str, #[case] cnf: &str) {
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(expr.to_dnf().to_string(), dnf);
+        assert_eq!(expr.to_cnf().to_string(), cnf);
+
+        for keys in [
+            vec![],
+            vec!["a"],
+            vec!["a", "c"],
+            vec!["b", "c"],
+            vec!["x1"],
+        ] {
+            assert_eq!(
+                expr.to_dnf().matches(&keys),
+                expr.matches(&keys),
+                "{keys:?}"
+            );
+            assert_eq!(
+                expr.to_cnf().matches(&keys),
+                expr.matches(&keys),
+                "{keys:?}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case("a", false, false)]
+    #[case("a || !a", true, false)]
+    #[case("a && !a", false, true)]
+    #[case("-a a", false, true)]
+    #[case("(a || b) && !a && !b", false, true)]
+    #[case("!(a && !a)", true, false)]
+    #[case("a* || !a*", true, false)]
+    // Terms are independent, `a` implying `a*` is not detected
+    #[case("!a || a*", false, false)]
+    fn test_tautology_and_contradiction(
+        #[case] input: &str,
+        #[case] tautology: bool,
+        #[case] contradiction: bool,
+    ) {
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(expr.is_tautology(), tautology);
+        assert_eq!(expr.is_contradiction(), contradiction);
+    }
+
+    #[rstest]
+    #[case("a", &["a"], &["a"], &[])]
+    #[case("a && !b && c*", &["a", "b", "c*"], &["a"], &["c*"])]
+    #[case("a && b || a && c", &["a", "b", "c"], &["a"], &[])]
+    #[case("(a || b) && (a || c)", &["a", "b", "c"], &[], &[])]
+    #[case("a || !b", &["a", "b"], &[], &[])]
+    #[case("t && (x || !x)", &["t", "x"], &["t"], &[])]
+    #[case("a && !a", &["a"], &[], &[])]
+    #[case("a* && (a1 || b?)", &["a*", "a1", "b?"], &[], &["a*"])]
+    fn test_referenced_and_required_keys(
+        #[case] input: &str,
+        #[case] referenced: &[&str],
+        #[case] required: &[&str],
+        #[case] required_patterns: &[&str],
+    ) {
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(
+            expr.referenced_keys().into_iter().collect::<Vec<_>>(),
+            referenced
+        );
+        let required_keys = expr.required_keys();
+        assert_eq!(required_keys.keys.into_iter().collect::<Vec<_>>(), required);
+        assert_eq!(
+            required_keys.patterns.into_iter().collect::<Vec<_>>(),
+            required_patterns
+        );
+    }
+
+    #[test]
+    fn test_constant_normal_forms() {
+        for normal_form in [
+            Expr::parse("a || !a").unwrap().to_dnf(),
+            Expr::parse("a || !a").unwrap().to_cnf(),
+        ] {
+            assert_eq!(normal_form.to_string(), "'' || !''");
+            assert!(normal_form.is_tautology());
+        }
+        for normal_form in [
+            Expr::parse("a && !a").unwrap().to_dnf(),
+            Expr::parse("a && !a").unwrap().to_cnf(),
+        ] {
+            assert_eq!(normal_form.to_string(), "'' && !''");
+            assert!(normal_form.is_contradiction());
+        }
+
+        assert!(Expr::And(vec![]).matches::<&str>(&[]));
+        assert!(!Expr::Or(vec![]).matches::<&str>(&[]));
+        assert_eq!(
+            Expr::parse(&Expr::And(vec![Expr::Or(vec![]), Expr::And(vec![])]).to_string())
+                .unwrap()
+                .to_dnf(),
+            Expr::contradiction()
+        );
+    }
+}
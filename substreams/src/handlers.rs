@@ -3,6 +3,85 @@
 //! This create exports useful macros that you can use to develop
 //! Substreams handlers. The goal of these macros is to significantly reduce boilerplate
 //! code and ensure that your handler are more readable
+//!
+//! ## Manifest validation
+//!
+//! When a `substreams.yaml` manifest is given, either with the `manifest = "substreams.yaml"`
+//! attribute argument of a handler macro or with the `SUBSTREAMS_MANIFEST` environment variable
+//! at build time (relative paths are resolved from the crate's directory), the handler macros
+//! check at compile time that:
+//!
+//! - the handler's module name exists in the manifest with the same kind (`map`, `store` or `blockIndex`),
+//! - the handler's inputs are in the same order and of the same kind as the module's inputs
+//!   (`source` or `map` protobuf messages, `params`, `store` in `get` or `deltas` mode),
+//! - the writable store type of a store handler matches the module's `updatePolicy` and `valueType`.
+//!
+//! The generated code includes the manifest with `include_str!`, so Cargo rebuilds the crate
+//! and validates the handlers again when the manifest changes.
+//!
+//! ## Handler inputs
+//!
+//! The host passes each input according to its kind, the handler macros build each argument
+//! through the [HandlerInput] trait of its type, whatever the type's name, so type aliases and
+//! any type implementing it work:
+//!
+//! - writable stores (`StoreSetInt64`, `StoreAppend<T>`, ...) are created by the handler,
+//! - readable stores (`StoreGetInt64`, `StoreGetProto<T>`, ...) are passed as their index,
+//! - `Deltas<T>` and `LazyDeltas<V>` are decoded from the store's deltas,
+//! - protobuf messages implementing [prost::Message], the `sf.substreams.v1.Clock` source
+//!   ([crate::clock::Clock]) included, are decoded from their bytes.
+//!
+//! `String` and `#[params]` arguments are the module's params, decoded through
+//! [crate::params::Params], and `u32` arguments of store handlers named `<store>_idx` are the
+//! raw index of store `<store>`.
+//!
+//! ```rust
+//! use substreams::prelude::*;
+//! # mod proto { pub type Custom = (); }
+//!
+//! type Balances = StoreAddBigInt;
+//! type Prices = StoreGetBigDecimal;
+//!
+//! #[substreams::handlers::store]
+//! fn store_balances(data: proto::Custom, prices: Prices, balances: Balances) {
+//!     unimplemented!("do something");
+//! }
+//! ```
+//!
+//! A type that isn't a handler input fails compilation at the argument:
+//!
+//! ```compile_fail
+//! # mod proto { pub type Custom = (); }
+//! struct Deltas;
+//!
+//! // error: the trait bound `Deltas: HandlerInput` is not satisfied
+//! #[substreams::handlers::map]
+//! fn map_deltas(deltas: Deltas) -> proto::Custom {
+//!     unimplemented!("do something");
+//! }
+//! ```
+
+use std::convert::TryFrom;
+
+use anyhow::Context;
+
+use crate::errors::Error;
+use crate::pb::substreams::store_delta::Operation;
+use crate::pb::substreams::{StoreDelta, StoreDeltas};
+use crate::proto;
+use crate::scalar::{BigDecimal, BigInt};
+use crate::store::{
+    Appender, Delta, DeltaError, Deltas, LazyDeltas, StoreAddBigDecimal, StoreAddBigInt,
+    StoreAddFloat64, StoreAddInt64, StoreAppend, StoreGet, StoreGetArray, StoreGetBigDecimal,
+    StoreGetBigInt, StoreGetFloat64, StoreGetInt64, StoreGetProto, StoreGetRaw, StoreGetString,
+    StoreGetValue, StoreMaxBigDecimal, StoreMaxBigInt, StoreMaxFloat64, StoreMaxInt64,
+    StoreMinBigDecimal, StoreMinBigInt, StoreMinFloat64, StoreMinInt64, StoreNew,
+    StoreSetBigDecimal, StoreSetBigInt, StoreSetFloat64, StoreSetIfNotExistsBigDecimal,
+    StoreSetIfNotExistsBigInt, StoreSetIfNotExistsFloat64, StoreSetIfNotExistsInt64,
+    StoreSetIfNotExistsProto, StoreSetIfNotExistsRaw, StoreSetIfNotExistsString,
+    StoreSetIfNotExistsValue, StoreSetInt64, StoreSetProto, StoreSetRaw, StoreSetString,
+    StoreSetValue, StoreValue,
+};
 
 /// Marks function to setup substreams map handler WASM boilerplate
 ///
@@ -45,10 +124,11 @@
 ///         }
 ///     };
 ///     let result = func();
-///     if result.is_err() {
-///         panic!(result.err().unwrap())
+///     match result {
+///         Ok(value) => substreams::output(value),
+///         // Sends the handler name, error kind and full error chain to the host
+///         Err(err) => substreams::errors::report_handler_error("map_handler", err),
 ///     }
-///     substreams::output(substreams::proto::encode(&result.unwrap()).unwrap());
 /// }
 ///
 /// ```
@@ -141,6 +221,23 @@
 ///     substreams::output(result);
 /// }
 /// ```
+///
+/// ## Attribute arguments
+///
+/// - `name = "..."` exports the handler under the given module name instead of the function's name.
+/// - `output = "proto:<message>"` (or `"bytes"`) declares the module's output type, compilation
+///   failing when the handler doesn't return that message (`Vec<u8>` for `bytes`) or, when
+///   validating against a manifest, when it differs from the module's `output.type`.
+///
+/// ```rust
+/// # mod eth { pub type Block = (); }
+/// # mod proto { pub type Custom = (); }
+///
+/// #[substreams::handlers::map(name = "map_transfers", output = "proto:my.Custom")]
+/// fn map_handler(blk: eth::Block) -> proto::Custom {
+///     unimplemented!("do something");
+/// }
+/// ```
 pub use substreams_macro::map;
 
 /// Marks function to setup substreams store handler WASM boilerplate
@@ -191,4 +288,450 @@ pub use substreams_macro::map;
 ///    }
 /// }
 /// ```
+///
+/// ## Usage with Result<(), ...>
+///
+/// A store handler may return a `Result<(), Error>` to use `?` in its body, a returned error
+/// is reported to the host like for map handlers (see [crate::errors::report_handler_error]).
+///
+/// ```rust
+/// use substreams::prelude::*;
+/// use substreams::store::StoreSetInt64;
+/// # mod proto { pub type Custom = String; }
+///
+/// #[substreams::handlers::store]
+/// fn store_counts(data: proto::Custom, s: StoreSetInt64) -> Result<(), substreams::errors::Error> {
+///     let count: i64 = data.parse()?;
+///     s.set(0, "count", &count);
+///     Ok(())
+/// }
+/// ```
+///
+/// ## Attribute arguments
+///
+/// - `name = "..."` exports the handler under the given module name instead of the function's name.
+/// - `update_policy = "..."` (`set`, `set_if_not_exists`, `add`, `min`, `max` or `append`) and
+///   `value_type = "..."` (`int64`, `float64`, `bigint`, `bigdecimal`, `string`, `bytes` or
+///   `proto:<message>`) declare the store as in the manifest, compilation fails if the
+///   writable store type does not match them.
+/// - `writable_store = "none"` declares a handler without a writable store input, compilation
+///   fails for a store handler without one otherwise.
+///
+/// ```rust
+/// use substreams::prelude::*;
+/// use substreams::store::StoreAddBigInt;
+/// # mod proto { pub type Custom = (); }
+///
+/// #[substreams::handlers::store(update_policy = "add", value_type = "bigint")]
+/// fn store_volumes(data: proto::Custom, s: StoreAddBigInt) {
+///     unimplemented!("do something");
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use substreams::prelude::*;
+/// use substreams::store::StoreSetBigInt;
+/// # mod proto { pub type Custom = (); }
+///
+/// // error: update policy `add` does not match the update policy of writable store `StoreSetBigInt`
+/// #[substreams::handlers::store(update_policy = "add", value_type = "bigint")]
+/// fn store_volumes(data: proto::Custom, s: StoreSetBigInt) {
+///     unimplemented!("do something");
+/// }
+/// ```
 pub use substreams_macro::store;
+
+/// Marks function to setup substreams index handler WASM boilerplate
+///
+/// An index handler is a map handler whose output is the block's [crate::index::Keys],
+/// it accepts the same return types as `#[substreams::handlers::map]` with the output type
+/// being anything converting into [crate::index::Keys] like a [crate::index::KeysBuilder].
+///
+/// ```rust
+/// # mod eth { pub type Block = (); }
+/// use substreams::index::KeysBuilder;
+///
+/// #[substreams::handlers::index]
+/// fn index_events(blk: eth::Block) -> Result<KeysBuilder, substreams::errors::Error> {
+///     let mut keys = KeysBuilder::new();
+///     keys.add_hex("evt_addr", [0xab, 0xcd]);
+///     Ok(keys)
+/// }
+/// ```
+pub use substreams_macro::index;
+
+/// The kind of a handler input, which decides how the host passes it and which module input
+/// of the manifest it can be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    /// The store a store handler writes to, not passed by the host, with its update policy and
+    /// value types as written in the manifest, `proto` standing for any `proto:<message>` and
+    /// an empty list meaning any value type.
+    WritableStore {
+        update_policy: &'static str,
+        value_types: &'static [&'static str],
+    },
+    /// A store given as input in `get` mode, passed as its index.
+    ReadableStore,
+    /// A store given as input in `deltas` mode.
+    Deltas,
+    /// The module's params, see [crate::params].
+    Params,
+    /// A protobuf message, from a source or a map module.
+    Proto,
+}
+
+/// HandlerInput is implemented by the types a handler can take as input: stores, deltas and
+/// protobuf messages. The handler macros build each argument through it, whatever the name of
+/// the argument's type, so a type not implementing it fails compilation at the argument.
+///
+/// `String` and `#[params]` arguments are decoded through [crate::params::Params] instead and
+/// `u32` arguments of store handlers named `<store>_idx` are raw store indexes.
+pub trait HandlerInput: Sized {
+    const KIND: InputKind;
+
+    /// The raw arguments the host passes for the input, `()` when it passes nothing.
+    type Ptr;
+    type Len;
+
+    fn from_host(ptr: Self::Ptr, len: Self::Len) -> Result<Self, Error>;
+}
+
+macro_rules! impl_writable_store_input {
+    ($($store:ty => $policy:literal [$($value:literal),*]),+ $(,)?) => {
+        $(
+            impl HandlerInput for $store {
+                const KIND: InputKind = InputKind::WritableStore {
+                    update_policy: $policy,
+                    value_types: &[$($value),*],
+                };
+
+                type Ptr = ();
+                type Len = ();
+
+                fn from_host(_: (), _: ()) -> Result<Self, Error> {
+                    Ok(<$store as StoreNew>::new())
+                }
+            }
+        )+
+    };
+}
+
+impl_writable_store_input!(
+    StoreSetRaw => "set" ["bytes", "string"],
+    StoreSetString => "set" ["string"],
+    StoreSetInt64 => "set" ["int64"],
+    StoreSetFloat64 => "set" ["float64"],
+    StoreSetBigDecimal => "set" ["bigdecimal"],
+    StoreSetBigInt => "set" ["bigint"],
+    StoreSetIfNotExistsRaw => "set_if_not_exists" ["bytes", "string"],
+    StoreSetIfNotExistsString => "set_if_not_exists" ["string"],
+    StoreSetIfNotExistsInt64 => "set_if_not_exists" ["int64"],
+    StoreSetIfNotExistsFloat64 => "set_if_not_exists" ["float64"],
+    StoreSetIfNotExistsBigDecimal => "set_if_not_exists" ["bigdecimal"],
+    StoreSetIfNotExistsBigInt => "set_if_not_exists" ["bigint"],
+    StoreAddInt64 => "add" ["int64"],
+    StoreAddFloat64 => "add" ["float64"],
+    StoreAddBigDecimal => "add" ["bigdecimal"],
+    StoreAddBigInt => "add" ["bigint"],
+    StoreMaxInt64 => "max" ["int64"],
+    StoreMaxFloat64 => "max" ["float64"],
+    StoreMaxBigDecimal => "max" ["bigdecimal"],
+    StoreMaxBigInt => "max" ["bigint"],
+    StoreMinInt64 => "min" ["int64"],
+    StoreMinFloat64 => "min" ["float64"],
+    StoreMinBigDecimal => "min" ["bigdecimal"],
+    StoreMinBigInt => "min" ["bigint"],
+);
+
+impl<V: Default + prost::Message> HandlerInput for StoreSetProto<V> {
+    const KIND: InputKind = InputKind::WritableStore {
+        update_policy: "set",
+        value_types: &["proto"],
+    };
+
+    type Ptr = ();
+    type Len = ();
+
+    fn from_host(_: (), _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreNew>::new())
+    }
+}
+
+impl<V: Default + prost::Message> HandlerInput for StoreSetIfNotExistsProto<V> {
+    const KIND: InputKind = InputKind::WritableStore {
+        update_policy: "set_if_not_exists",
+        value_types: &["proto"],
+    };
+
+    type Ptr = ();
+    type Len = ();
+
+    fn from_host(_: (), _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreNew>::new())
+    }
+}
+
+impl<V: StoreValue> HandlerInput for StoreSetValue<V> {
+    const KIND: InputKind = InputKind::WritableStore {
+        update_policy: "set",
+        value_types: &[],
+    };
+
+    type Ptr = ();
+    type Len = ();
+
+    fn from_host(_: (), _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreNew>::new())
+    }
+}
+
+impl<V: StoreValue> HandlerInput for StoreSetIfNotExistsValue<V> {
+    const KIND: InputKind = InputKind::WritableStore {
+        update_policy: "set_if_not_exists",
+        value_types: &[],
+    };
+
+    type Ptr = ();
+    type Len = ();
+
+    fn from_host(_: (), _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreNew>::new())
+    }
+}
+
+impl<T: Into<String>> HandlerInput for StoreAppend<T> {
+    const KIND: InputKind = InputKind::WritableStore {
+        update_policy: "append",
+        value_types: &[],
+    };
+
+    type Ptr = ();
+    type Len = ();
+
+    fn from_host(_: (), _: ()) -> Result<Self, Error> {
+        Ok(<StoreAppend<T> as Appender<T>>::new())
+    }
+}
+
+macro_rules! impl_readable_store_input {
+    ($($store:ty => $value:ty),+ $(,)?) => {
+        $(
+            impl HandlerInput for $store {
+                const KIND: InputKind = InputKind::ReadableStore;
+
+                type Ptr = u32;
+                type Len = ();
+
+                fn from_host(idx: u32, _: ()) -> Result<Self, Error> {
+                    Ok(<$store as StoreGet<$value>>::new(idx))
+                }
+            }
+        )+
+    };
+}
+
+impl_readable_store_input!(
+    StoreGetRaw => Vec<u8>,
+    StoreGetString => String,
+    StoreGetInt64 => i64,
+    StoreGetFloat64 => f64,
+    StoreGetBigDecimal => BigDecimal,
+    StoreGetBigInt => BigInt,
+);
+
+impl<T: Into<String> + From<String>> HandlerInput for StoreGetArray<T> {
+    const KIND: InputKind = InputKind::ReadableStore;
+
+    type Ptr = u32;
+    type Len = ();
+
+    fn from_host(idx: u32, _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreGet<Vec<T>>>::new(idx))
+    }
+}
+
+impl<T: Default + prost::Message> HandlerInput for StoreGetProto<T> {
+    const KIND: InputKind = InputKind::ReadableStore;
+
+    type Ptr = u32;
+    type Len = ();
+
+    fn from_host(idx: u32, _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreGet<T>>::new(idx))
+    }
+}
+
+impl<V: StoreValue> HandlerInput for StoreGetValue<V> {
+    const KIND: InputKind = InputKind::ReadableStore;
+
+    type Ptr = u32;
+    type Len = ();
+
+    fn from_host(idx: u32, _: ()) -> Result<Self, Error> {
+        Ok(<Self as StoreGet<V>>::new(idx))
+    }
+}
+
+/// Deltas with an operation unknown to this version of the crate, sent by a newer engine, are
+/// skipped. Any other delta that cannot be converted is reported to the host by the generated
+/// handler code, see [crate::errors::report_handler_error].
+impl<T: Delta + for<'a> TryFrom<&'a StoreDelta, Error = DeltaError>> HandlerInput for Deltas<T> {
+    const KIND: InputKind = InputKind::Deltas;
+
+    type Ptr = *mut u8;
+    type Len = usize;
+
+    fn from_host(ptr: *mut u8, len: usize) -> Result<Self, Error> {
+        Ok(Deltas::try_new(known_operations(
+            decode::<StoreDeltas>(ptr, len)?.deltas,
+        ))?)
+    }
+}
+
+/// Deltas with an operation unknown to this version of the crate are skipped, see [Deltas].
+impl<V: StoreValue> HandlerInput for LazyDeltas<V> {
+    const KIND: InputKind = InputKind::Deltas;
+
+    type Ptr = *mut u8;
+    type Len = usize;
+
+    fn from_host(ptr: *mut u8, len: usize) -> Result<Self, Error> {
+        Ok(LazyDeltas::new(known_operations(
+            decode::<StoreDeltas>(ptr, len)?.deltas,
+        )))
+    }
+}
+
+impl<T: Default + prost::Message> HandlerInput for T {
+    const KIND: InputKind = InputKind::Proto;
+
+    type Ptr = *mut u8;
+    type Len = usize;
+
+    fn from_host(ptr: *mut u8, len: usize) -> Result<Self, Error> {
+        decode(ptr, len)
+    }
+}
+
+fn decode<T: Default + prost::Message>(ptr: *mut u8, len: usize) -> Result<T, Error> {
+    proto::decode_ptr(ptr, len).with_context(|| {
+        format!(
+            "Unable to decode Protobuf data ({} bytes) to '{}' message's struct",
+            len,
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn known_operations(deltas: Vec<StoreDelta>) -> Vec<StoreDelta> {
+    deltas
+        .into_iter()
+        .filter(|delta| Operation::from_i32(delta.operation).is_some())
+        .collect()
+}
+
+/// Returns the number of writable stores among the `kinds` of a handler's inputs, used by the
+/// handler macros in constant assertions.
+#[doc(hidden)]
+pub const fn writable_stores(kinds: &[InputKind]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < kinds.len() {
+        if matches!(kinds[i], InputKind::WritableStore { .. }) {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Returns the position in the manifest's module inputs of the handler input at `index`, the
+/// writable stores before it not being declared there, used by the handler macros in constant
+/// assertions.
+#[doc(hidden)]
+pub const fn manifest_input_index(kinds: &[InputKind], index: usize) -> usize {
+    let mut i = 0;
+    let mut position = 0;
+    while i < index {
+        if !matches!(kinds[i], InputKind::WritableStore { .. }) {
+            position += 1;
+        }
+        i += 1;
+    }
+    position
+}
+
+/// Returns true if an input of kind `kind` can be a module input of kind `declared` in the
+/// manifest, writable stores not being declared there, used by the handler macros in constant
+/// assertions.
+#[doc(hidden)]
+pub const fn input_kind_matches(kind: InputKind, declared: InputKind) -> bool {
+    matches!(
+        (kind, declared),
+        (InputKind::WritableStore { .. }, _)
+            | (InputKind::ReadableStore, InputKind::ReadableStore)
+            | (InputKind::Deltas, InputKind::Deltas)
+            | (InputKind::Params, InputKind::Params)
+            | (InputKind::Proto, InputKind::Proto)
+    )
+}
+
+/// Returns true if `kind` is a readable store, used by the handler macros in constant
+/// assertions.
+#[doc(hidden)]
+pub const fn is_readable_store(kind: InputKind) -> bool {
+    matches!(kind, InputKind::ReadableStore)
+}
+
+/// Returns true if `kind` is not a writable store or if `update_policy` is its update policy,
+/// used by the handler macros in constant assertions.
+#[doc(hidden)]
+pub const fn update_policy_matches(kind: InputKind, update_policy: &str) -> bool {
+    match kind {
+        InputKind::WritableStore {
+            update_policy: expected,
+            ..
+        } => str_eq(expected, update_policy),
+        _ => true,
+    }
+}
+
+/// Returns true if `kind` is not a writable store or if the value type `value_type` (`proto`
+/// for `proto:<message>`) is one of its value types, used by the handler macros in constant
+/// assertions.
+#[doc(hidden)]
+pub const fn value_type_matches(kind: InputKind, value_type: &str) -> bool {
+    let expected = match kind {
+        InputKind::WritableStore { value_types, .. } => value_types,
+        _ => return true,
+    };
+    if expected.is_empty() {
+        return true;
+    }
+
+    let mut i = 0;
+    while i < expected.len() {
+        if str_eq(expected[i], value_type) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
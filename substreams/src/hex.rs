@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 /// Hex is a simple wrapper type that you can use to wrap your type so that it
 /// prints in lower hexadecimal format when use as a formatting argument.
@@ -21,6 +22,17 @@ use std::fmt;
 ///
 /// let encode = Hex::encode(&[0xabu8, 0xcdu8, 0xefu8]);
 /// ```
+///
+/// An owned `Hex<Vec<u8>>` can be parsed back from its string form, with or without
+/// the `0x` prefix, which makes it usable to extract typed key segments:
+///
+/// ```
+/// use substreams::Hex;
+///
+/// let address: Hex<Vec<u8>> = "0xabcdef".parse().unwrap();
+/// assert_eq!(address.0, vec![0xabu8, 0xcdu8, 0xefu8]);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Hex<T>(pub T);
 
 impl<T: AsRef<[u8]>> Hex<T> {
@@ -39,6 +51,14 @@ impl<T: AsRef<[u8]>> Hex<T> {
     }
 }
 
+impl FromStr for Hex<Vec<u8>> {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Hex::decode(s).map(Hex)
+    }
+}
+
 impl<T: AsRef<[u8]>> fmt::Debug for Hex<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write_lower_hex(self.0.as_ref(), f)
@@ -77,12 +97,7 @@ const LOWER_HEX_BYTES: [&str; 256] = [
 ];
 
 fn remove_hex_prefix(data: &[u8]) -> &[u8] {
-    // Removing 0x prefix. "0" = 48, "x" = 120 (UTF-8)
-    if data[0] == (48 as u8) && data[1] == (120 as u8) {
-        return &data[2..data.len()];
-    }
-
-    return data;
+    data.strip_prefix(b"0x").unwrap_or(data)
 }
 
 fn write_lower_hex(input: &[u8], mut w: impl std::fmt::Write) -> fmt::Result {
@@ -109,6 +124,7 @@ fn encode_lower_hex<T: AsRef<[u8]>>(input: T) -> String {
 mod tests {
     use crate::hex::encode_lower_hex;
     use crate::Hex;
+    use std::str::FromStr;
 
     #[test]
     fn it_encode_lower_hex_correctly() {
@@ -152,4 +168,12 @@ mod tests {
         let result = Hex::decode(input).unwrap();
         assert_eq!(Hex::encode(result), expected_result)
     }
+
+    #[test]
+    fn it_parse_from_str_correctly() {
+        assert_eq!(Hex::from_str("0x6e8b").unwrap().0, vec![110u8, 139]);
+        assert_eq!(Hex::from_str("6e8b").unwrap().0, vec![110u8, 139]);
+        assert_eq!(Hex::from_str("").unwrap().0, Vec::<u8>::new());
+        assert!(Hex::from_str("0xzz").is_err());
+    }
 }
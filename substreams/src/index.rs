@@ -0,0 +1,149 @@
+//! The index module contains helpers to produce the [Keys] of a block index module.
+//!
+//! An index module emits, for each block, the set of keys describing the block's content,
+//! which are then used by `blockFilter` expressions (see [crate::expr_parser]) to skip
+//! blocks. Keys usually follow a `<type>:<value>` convention, for example `evt_addr:<address>`
+//! or `evt_sig:<signature>`, the [KeysBuilder] helps keeping those consistent:
+//!
+//! ```rust
+//! use substreams::index::{Keys, KeysBuilder};
+//!
+//! #[substreams::handlers::index]
+//! fn index_events(block: substreams::pb::substreams::Clock) -> KeysBuilder {
+//!     let mut keys = KeysBuilder::new();
+//!     keys.add_typed("block", block.number % 2)
+//!         .add_hex("evt_addr", [0xabu8, 0xcd])
+//!         .add_hex_str("evt_addr", "0xABCD")
+//!         .add("transfer");
+//!     keys
+//! }
+//!
+//! let mut keys = KeysBuilder::new();
+//! keys.add_hex("evt_addr", [0xabu8, 0xcd]).add_hex_str("evt_addr", "0xABCD").add("transfer");
+//! assert_eq!(keys.build().keys, vec!["evt_addr:abcd", "transfer"]);
+//! ```
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::iter::FromIterator;
+
+pub use crate::pb::sf::substreams::index::v1::Keys;
+
+/// KeysBuilder accumulates the keys of a block, the built [Keys] are sorted and deduplicated
+/// and empty keys are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeysBuilder {
+    keys: BTreeSet<String>,
+}
+
+impl KeysBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` as-is.
+    pub fn add<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        let key = key.into();
+        if !key.is_empty() {
+            self.keys.insert(key);
+        }
+        self
+    }
+
+    /// Adds a `<kind>:<value>` key.
+    pub fn add_typed<V: Display>(&mut self, kind: &str, value: V) -> &mut Self {
+        self.add(format!("{}:{}", kind, value))
+    }
+
+    /// Adds a `<kind>:<hex>` key, bytes being encoded as lower hexadecimal without `0x` prefix.
+    pub fn add_hex<B: AsRef<[u8]>>(&mut self, kind: &str, bytes: B) -> &mut Self {
+        self.add_typed(kind, crate::Hex(bytes))
+    }
+
+    /// Adds a `<kind>:<hex>` key from an already hex encoded value, normalized like
+    /// [KeysBuilder::add_hex] by removing the `0x` prefix and lowercasing it.
+    pub fn add_hex_str(&mut self, kind: &str, hex: &str) -> &mut Self {
+        let hex = hex
+            .strip_prefix("0x")
+            .or_else(|| hex.strip_prefix("0X"))
+            .unwrap_or(hex);
+
+        self.add_typed(kind, hex.to_lowercase())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn build(self) -> Keys {
+        Keys {
+            keys: self.keys.into_iter().collect(),
+        }
+    }
+}
+
+impl<K: Into<String>> Extend<K> for KeysBuilder {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.add(key);
+        }
+    }
+}
+
+impl<K: Into<String>> FromIterator<K> for KeysBuilder {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut builder = KeysBuilder::new();
+        builder.extend(iter);
+        builder
+    }
+}
+
+impl From<KeysBuilder> for Keys {
+    fn from(builder: KeysBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl From<Vec<String>> for Keys {
+    fn from(keys: Vec<String>) -> Self {
+        keys.into_iter().collect::<KeysBuilder>().build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Keys, KeysBuilder};
+
+    #[test]
+    fn keys_builder_normalizes() {
+        let mut builder = KeysBuilder::new();
+        builder
+            .add("z")
+            .add("")
+            .add_typed("block", 12)
+            .add_hex("addr", [0x0au8, 0xff])
+            .add_hex_str("addr", "0x0AFF")
+            .add_hex_str("addr", "0Xbeef");
+        builder.extend(vec!["a", "z"]);
+
+        assert_eq!(builder.len(), 5);
+        assert!(builder.contains("addr:0aff"));
+        assert_eq!(
+            builder.build().keys,
+            vec!["a", "addr:0aff", "addr:beef", "block:12", "z"]
+        );
+    }
+
+    #[test]
+    fn keys_from_vec() {
+        let keys = Keys::from(vec!["b".to_string(), "a".to_string(), "b".to_string()]);
+        assert_eq!(keys.keys, vec!["a", "b"]);
+    }
+}
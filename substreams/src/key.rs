@@ -36,8 +36,475 @@
 //! }
 //! ```
 //!
+//! Segments can also be parsed directly into typed values, either one at a time with
+//! [Delta::segment] or all at once with [Delta::key_parts] which checks that the key has
+//! exactly as many segments as the requested tuple:
+//!
+//! ```rust
+//! use substreams::key::KeyError;
+//! use substreams::store::{Delta, Deltas, DeltaBigDecimal};
+//!
+//! fn db_out(deltas: Deltas<DeltaBigDecimal>) -> Result<(), KeyError> {
+//!     for delta in deltas.into_iter() {
+//!         let (kind, pool, day): (String, String, u64) = delta.key_parts()?;
+//!         let day: u64 = delta.segment(2)?;
+//!         // Do something for this kind, pool and day
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Keys can be built with the [Key] builder instead of `format!`, segments containing the
+//! `:` separator are escaped so that [Key::parse] gives back the exact same segments:
+//!
+//! ```rust
+//! use substreams::key::Key;
+//! use substreams::scalar::BigInt;
+//!
+//! let key = Key::new()
+//!     .push("pool")
+//!     .push_hex(&[0xabu8, 0xcd])
+//!     .push(BigInt::from(42))
+//!     .push("a:b");
+//! assert_eq!(key.to_string(), "pool:abcd:42:a%3Ab");
+//!
+//! let parsed = Key::parse("pool:abcd:42:a%3Ab").unwrap();
+//! assert_eq!(parsed.segments(), &["pool", "abcd", "42", "a:b"]);
+//! assert_eq!(parsed.segment::<u64>(2).unwrap(), 42);
+//! ```
+//!
 use crate::prelude::Delta;
-use std::io::BufRead;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Derives `to_key`, `from_key` and `prefix` for a struct or an enum whose fields implement
+/// [Display] and [FromStr]. Each variant gets a `<VARIANT>_PREFIX` constant holding its first
+/// key segment (snake_case variant name unless overridden with `#[store_key(prefix = "...")]`)
+/// and a `<VARIANT>_DELETE_PREFIX` constant with the trailing separator for `delete_prefix`.
+/// Structs get `PREFIX` and `DELETE_PREFIX`. Prefixes must be unique among the variants and
+/// cannot contain `:` or `%`, which are escaped in keys.
+///
+/// ```rust
+/// use substreams::key::StoreKey;
+/// use substreams::Hex;
+///
+/// #[derive(StoreKey, Debug, PartialEq)]
+/// enum Key {
+///     Pool { address: Hex<Vec<u8>> },
+///     #[store_key(prefix = "pos")]
+///     Position { pool: String, id: u64 },
+/// }
+///
+/// let key = Key::Position { pool: "a:b".to_string(), id: 7 };
+/// assert_eq!(key.to_key(), "pos:a%3Ab:7");
+/// assert_eq!(Key::from_key("pos:a%3Ab:7").unwrap(), key);
+///
+/// let pool = Key::Pool { address: Hex(vec![0xab, 0xcd]) };
+/// assert_eq!(pool.to_key(), "pool:abcd");
+/// assert_eq!(pool.prefix(), Key::POOL_PREFIX);
+/// assert_eq!(Key::POSITION_DELETE_PREFIX, "pos:");
+/// assert!(Key::from_key("unknown:1").is_err());
+/// ```
+pub use substreams_macro::StoreKey;
+
+/// `KeyError` is returned when a key cannot be decoded into typed segments.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum KeyError {
+    #[error("key {key} has no segment at index {index}")]
+    MissingSegment { key: String, index: usize },
+    #[error("segment {index} ({segment}) of key {key} is invalid: {reason}")]
+    InvalidSegment {
+        key: String,
+        index: usize,
+        segment: String,
+        reason: String,
+    },
+    #[error("key {key} has {actual} segments, expected {expected}")]
+    SegmentCount {
+        key: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("key {key} has an invalid escape sequence at byte {position}")]
+    InvalidEscape { key: String, position: usize },
+    #[error("key {key} does not start with any known prefix")]
+    UnknownPrefix { key: String },
+    #[error("separator {separator:?} is the escape character of {escaping:?} escaping")]
+    InvalidSeparator { separator: char, escaping: Escaping },
+}
+
+/// Escaping defines how the separator is encoded when it appears inside a [Key] segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escaping {
+    /// Segments are joined as-is, a segment containing the separator does not round-trip.
+    None,
+    /// `%` is encoded as `%25` and the separator as its percent-encoded UTF-8 bytes
+    /// (`%3A` for `:`), the default.
+    Percent,
+    /// `\` is encoded as `\\` and the separator is prefixed by `\` (`\:` for `:`).
+    Backslash,
+}
+
+impl Default for Escaping {
+    fn default() -> Self {
+        Escaping::Percent
+    }
+}
+
+impl Escaping {
+    /// The character introducing an escape sequence, which cannot be used as a separator.
+    pub fn escape_char(&self) -> Option<char> {
+        match self {
+            Escaping::None => None,
+            Escaping::Percent => Some('%'),
+            Escaping::Backslash => Some('\\'),
+        }
+    }
+
+    fn escape_into(&self, segment: &str, separator: char, out: &mut String) {
+        for c in segment.chars() {
+            match (self, c) {
+                (Escaping::Percent, '%') => out.push_str("%25"),
+                (Escaping::Percent, c) if c == separator => {
+                    for byte in c.to_string().bytes() {
+                        out.push_str(&format!("%{:02X}", byte));
+                    }
+                }
+                (Escaping::Backslash, '\\') => out.push_str("\\\\"),
+                (Escaping::Backslash, c) if c == separator => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+
+    fn split(&self, key: &str, separator: char) -> Result<Vec<String>, KeyError> {
+        let invalid = |position| KeyError::InvalidEscape {
+            key: key.to_string(),
+            position,
+        };
+
+        match self {
+            Escaping::None => Ok(key.split(separator).map(String::from).collect()),
+            // Percent escaping never leaves a raw separator inside a segment
+            Escaping::Percent => {
+                let segments = Segments::with_separator(key, separator);
+                let mut offset = 0;
+
+                segments
+                    .iter()
+                    .map(|segment| {
+                        let decoded = percent_unescape(segment, separator)
+                            .map_err(|position| invalid(offset + position));
+                        offset += segment.len() + separator.len_utf8();
+                        decoded
+                    })
+                    .collect()
+            }
+            Escaping::Backslash => {
+                let mut segments = vec![String::new()];
+                let mut chars = key.char_indices();
+                while let Some((position, c)) = chars.next() {
+                    let current = segments.last_mut().expect("segments is never empty");
+                    match c {
+                        c if c == separator => segments.push(String::new()),
+                        '\\' => match chars.next() {
+                            Some((_, c)) if c == '\\' || c == separator => current.push(c),
+                            _ => return Err(invalid(position)),
+                        },
+                        _ => current.push(c),
+                    }
+                }
+
+                Ok(segments)
+            }
+        }
+    }
+}
+
+// Reverses `Escaping::Percent`, only accepting the `%25` and separator escapes built by
+// `escape_into`, returns the byte position of the first other escape on error
+fn percent_unescape(segment: &str, separator: char) -> Result<String, usize> {
+    let mut escaped_separator = String::new();
+    Escaping::Percent.escape_into(&separator.to_string(), separator, &mut escaped_separator);
+
+    let mut out = String::with_capacity(segment.len());
+    let mut rest = segment;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+
+        let escape = &rest[start..];
+        rest = if let Some(after) = escape.strip_prefix("%25") {
+            out.push('%');
+            after
+        } else if let Some(after) = escape.strip_prefix(escaped_separator.as_str()) {
+            out.push(separator);
+            after
+        } else {
+            return Err(segment.len() - escape.len());
+        };
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// KeyFormat describes the separator and [Escaping] used by a store's keys, so that the
+/// segment helpers of this module can be used on keys not following the default `:`
+/// convention.
+///
+/// ```rust
+/// use substreams::key::{Escaping, KeyFormat};
+///
+/// let format = KeyFormat::new('/');
+/// assert_eq!(format.segment_at("pool/0x01/day", 1), "0x01");
+/// assert_eq!(format.last_segment("pool/0x01/day"), "day");
+///
+/// let key = format.key().push("pool").push("a/b").to_string();
+/// assert_eq!(key, "pool/a%2Fb");
+/// assert_eq!(format.parse(&key).unwrap().segments(), &["pool", "a/b"]);
+///
+/// let (kind, id): (String, u64) = KeyFormat::new('|').key_parts("pool|42").unwrap();
+/// assert_eq!(id, 42);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFormat {
+    pub separator: char,
+    pub escaping: Escaping,
+}
+
+impl Default for KeyFormat {
+    fn default() -> Self {
+        KeyFormat {
+            separator: ':',
+            escaping: Escaping::default(),
+        }
+    }
+}
+
+impl KeyFormat {
+    /// Creates a format with the given separator using [Escaping::Percent].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `separator` is `%`, see [KeyFormat::try_new].
+    pub fn new(separator: char) -> Self {
+        Self::try_new(separator, Escaping::default()).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a format with the given separator and escaping, failing with
+    /// [KeyError::InvalidSeparator] when the separator is the escape character of `escaping`,
+    /// segments could not be split back unambiguously otherwise.
+    pub fn try_new(separator: char, escaping: Escaping) -> Result<Self, KeyError> {
+        if escaping.escape_char() == Some(separator) {
+            return Err(KeyError::InvalidSeparator {
+                separator,
+                escaping,
+            });
+        }
+
+        Ok(KeyFormat {
+            separator,
+            escaping,
+        })
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the separator is the escape character of `escaping`, see [KeyFormat::try_new].
+    pub fn with_escaping(self, escaping: Escaping) -> Self {
+        Self::try_new(self.separator, escaping).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates an empty [Key] builder using this format.
+    pub fn key(&self) -> Key {
+        Key::with_format(*self)
+    }
+
+    /// Parses a key built with this format, see [Key::parse].
+    pub fn parse(&self, key: &str) -> Result<Key, KeyError> {
+        Key::parse_with_format(key, *self)
+    }
+
+    pub fn segments<'a>(&self, key: &'a str) -> Segments<'a> {
+        Segments::with_separator(key, self.separator)
+    }
+
+    pub fn segment_at<'a>(&self, key: &'a str, index: usize) -> &'a str {
+        self.try_segment_at(key, index).unwrap_or_else(|| {
+            panic!(
+                "Unable to extract segment index {} out of key {}",
+                index, key
+            )
+        })
+    }
+
+    pub fn first_segment<'a>(&self, key: &'a str) -> &'a str {
+        self.segment_at(key, 0)
+    }
+
+    pub fn last_segment<'a>(&self, key: &'a str) -> &'a str {
+        segment_of(key, None, self.separator)
+    }
+
+    pub fn try_segment_at<'a>(&self, key: &'a str, index: usize) -> Option<&'a str> {
+        find_segment(key, Some(index), self.separator)
+    }
+
+    pub fn try_first_segment<'a>(&self, key: &'a str) -> Option<&'a str> {
+        self.try_segment_at(key, 0)
+    }
+
+    pub fn try_last_segment<'a>(&self, key: &'a str) -> Option<&'a str> {
+        find_segment(key, None, self.separator)
+    }
+
+    /// Parses the segment at `index` of `key` into `T`.
+    pub fn try_parse_segment_at<T>(&self, key: &str, index: usize) -> Result<T, KeyError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let segment = self
+            .try_segment_at(key, index)
+            .ok_or_else(|| KeyError::MissingSegment {
+                key: key.to_string(),
+                index,
+            })?;
+
+        parse_segment(key, index, segment)
+    }
+
+    /// Parses the whole key into a tuple of typed segments, see [FromKeyParts].
+    pub fn key_parts<T: FromKeyParts>(&self, key: &str) -> Result<T, KeyError> {
+        T::from_segments(&self.segments(key))
+    }
+}
+
+/// Key is a builder for separated store keys, see the [module](crate::key) documentation.
+///
+/// Like [Segments], `Key::parse("")` returns a key made of a single empty segment, which
+/// displays as `""`. `Key::new()`, the key without any segment, also displays as `""` and
+/// only becomes a valid key once a segment is pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Key {
+    segments: Vec<String>,
+    format: KeyFormat,
+}
+
+impl Key {
+    /// Creates an empty `:` separated key using [Escaping::Percent].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty `:` separated key using the given [Escaping].
+    pub fn with_escaping(escaping: Escaping) -> Self {
+        Self::with_format(KeyFormat::default().with_escaping(escaping))
+    }
+
+    /// Creates an empty key using the given [KeyFormat].
+    pub fn with_format(format: KeyFormat) -> Self {
+        Self {
+            segments: Vec::new(),
+            format,
+        }
+    }
+
+    /// Appends a segment, anything implementing [Display] like strings, numbers or
+    /// [BigInt](crate::scalar::BigInt) can be pushed.
+    pub fn push<T: Display>(mut self, segment: T) -> Self {
+        self.segments.push(segment.to_string());
+        self
+    }
+
+    /// Appends bytes as a lower hexadecimal segment without `0x` prefix.
+    pub fn push_hex<T: AsRef<[u8]>>(self, bytes: T) -> Self {
+        let segment = crate::Hex::encode(bytes);
+        self.push(segment)
+    }
+
+    /// Parses a `:` separated key built with [Escaping::Percent], reversing the escaping
+    /// of each segment.
+    pub fn parse(key: &str) -> Result<Self, KeyError> {
+        Self::parse_with_format(key, KeyFormat::default())
+    }
+
+    /// Parses a `:` separated key built with the given [Escaping].
+    pub fn parse_with_escaping(key: &str, escaping: Escaping) -> Result<Self, KeyError> {
+        Self::parse_with_format(key, KeyFormat::default().with_escaping(escaping))
+    }
+
+    /// Parses a key built with the given [KeyFormat].
+    pub fn parse_with_format(key: &str, format: KeyFormat) -> Result<Self, KeyError> {
+        Ok(Self {
+            segments: format.escaping.split(key, format.separator)?,
+            format,
+        })
+    }
+
+    /// The unescaped segments of the key.
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// Parses the unescaped segment at `index` into `T`.
+    pub fn segment<T>(&self, index: usize) -> Result<T, KeyError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let segment = self
+            .segments
+            .get(index)
+            .ok_or_else(|| KeyError::MissingSegment {
+                key: self.to_string(),
+                index,
+            })?;
+
+        // The key is only displayed for the error, parsing a segment doesn't allocate it
+        T::from_str(segment).map_err(|err| KeyError::InvalidSegment {
+            key: self.to_string(),
+            index,
+            segment: segment.clone(),
+            reason: err.to_string(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                out.push(self.format.separator);
+            }
+            self.format
+                .escaping
+                .escape_into(segment, self.format.separator, &mut out);
+        }
+
+        f.write_str(&out)
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.to_string()
+    }
+}
 
 pub fn segment_at(key: &String, index: usize) -> &str {
     try_segment_at(key, index).unwrap_or_else(|| {
@@ -49,26 +516,7 @@ pub fn segment_at(key: &String, index: usize) -> &str {
 }
 
 pub fn segment_at_owned(key: String, index: usize) -> String {
-    let mut parts = std::io::Cursor::new(key.into_bytes()).split(b':');
-
-    // Use of unwrap because those who want to check errors must use the try_ version
-    let segment_result = parts.nth(index).unwrap_or_else(|| {
-        panic!(
-            "Unable to extract segment index {} for key {}",
-            index,
-            parts
-                .map(|x| String::from_utf8(x.expect("Cursor is infallible"))
-                    .expect("Must be valid UTF-8 here as we split an initially valid String"))
-                .collect::<Vec<_>>()
-                .join(":"),
-        )
-    });
-
-    // Use of expect because I/O is infallible as we own the memory location already (no external I/O is done)
-    let segment = segment_result.expect("Cursor is infallible");
-
-    String::from_utf8(segment)
-        .expect("Must be valid UTF-8 here as we split an initially valid String")
+    segment_at(&key, index).to_string()
 }
 
 pub fn first_segment(key: &String) -> &str {
@@ -81,11 +529,7 @@ pub fn last_segment(key: &String) -> &str {
 }
 
 pub fn try_segment_at(key: &String, index: usize) -> Option<&str> {
-    let val = key.split(":").nth(index);
-    match val {
-        Some(val) => Some(val),
-        None => None,
-    }
+    find_segment(key, Some(index), ':')
 }
 
 pub fn try_first_segment(key: &String) -> Option<&str> {
@@ -93,9 +537,153 @@ pub fn try_first_segment(key: &String) -> Option<&str> {
 }
 
 pub fn try_last_segment(key: &String) -> Option<&str> {
-    match key.split(":").last() {
-        Some(val) => Some(val),
-        None => None,
+    find_segment(key, None, ':')
+}
+
+const INLINE_SEGMENTS: usize = 8;
+
+/// Segments holds the segment boundaries of a key, computed once, so that any segment can
+/// then be accessed in O(1). Keys of up to 8 segments are handled without allocating.
+///
+/// ```rust
+/// use substreams::key::Segments;
+///
+/// let segments = Segments::new("pool:0x01:day:19000");
+/// assert_eq!(segments.len(), 4);
+/// assert_eq!(segments.get(1), Some("0x01"));
+/// assert_eq!(segments.last(), "19000");
+/// assert_eq!(segments.get_back(1), Some("day"));
+/// assert_eq!(segments.iter().rev().collect::<Vec<_>>(), vec!["19000", "day", "0x01", "pool"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    key: &'a str,
+    len: usize,
+    separator_len: usize,
+    // Start offset of each segment, the first `INLINE_SEGMENTS` are kept inline
+    inline: [usize; INLINE_SEGMENTS],
+    spill: Vec<usize>,
+}
+
+impl<'a> Segments<'a> {
+    pub fn new(key: &'a str) -> Self {
+        Self::with_separator(key, ':')
+    }
+
+    pub fn with_separator(key: &'a str, separator: char) -> Self {
+        let mut segments = Segments {
+            key,
+            len: 1,
+            separator_len: separator.len_utf8(),
+            inline: [0; INLINE_SEGMENTS],
+            spill: Vec::new(),
+        };
+
+        for (offset, _) in key.match_indices(separator) {
+            let start = offset + segments.separator_len;
+            if segments.len < INLINE_SEGMENTS {
+                segments.inline[segments.len] = start;
+            } else {
+                segments.spill.push(start);
+            }
+            segments.len += 1;
+        }
+
+        segments
+    }
+
+    fn start(&self, index: usize) -> usize {
+        if index < INLINE_SEGMENTS {
+            self.inline[index]
+        } else {
+            self.spill[index - INLINE_SEGMENTS]
+        }
+    }
+
+    /// Returns the segment at `index`, `None` if the key has fewer segments.
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        if index >= self.len {
+            return None;
+        }
+
+        let end = if index + 1 < self.len {
+            self.start(index + 1) - self.separator_len
+        } else {
+            self.key.len()
+        };
+
+        Some(&self.key[self.start(index)..end])
+    }
+
+    /// Returns the segment at `index` counting from the end, `get_back(0)` being the last one.
+    pub fn get_back(&self, index: usize) -> Option<&'a str> {
+        if index >= self.len {
+            return None;
+        }
+
+        self.get(self.len - 1 - index)
+    }
+
+    pub fn first(&self) -> &'a str {
+        self.get(0).expect("a key always has at least one segment")
+    }
+
+    pub fn last(&self) -> &'a str {
+        self.get(self.len - 1)
+            .expect("a key always has at least one segment")
+    }
+
+    /// Number of segments, a key always has at least one (possibly empty) segment.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`, present for consistency with [Segments::len].
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the segments, use `.rev()` to iterate from the last one.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &'a str> + ExactSizeIterator + '_ {
+        (0..self.len).map(move |index| self.get(index).expect("index is within bounds"))
+    }
+
+    /// The key these segments were computed from.
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+}
+
+// Some(x) means the xth segment, None means the last segment of key. The key is only scanned
+// up to the requested segment, or from its end for the last one.
+fn find_segment(key: &str, at: Option<usize>, separator: char) -> Option<&str> {
+    match at {
+        Some(at) => key.split(separator).nth(at),
+        None => key.rsplit(separator).next(),
+    }
+}
+
+fn segment_of(key: &str, at: Option<usize>, separator: char) -> &str {
+    find_segment(key, at, separator).unwrap_or_else(|| {
+        panic!(
+            "Unable to extract segment index {} out of key {}",
+            at.unwrap_or_default(),
+            key
+        )
+    })
+}
+
+// The segment compared by the `DeltaExt` segment filters, looked up through [Segments]
+fn compared_segment(key: &str, at: Option<usize>, separator: char) -> &str {
+    let segments = Segments::with_separator(key, separator);
+    match at {
+        Some(index) => segments.get(index).unwrap_or_else(|| {
+            panic!(
+                "Unable to extract segment index {} out of key {}",
+                index, key
+            )
+        }),
+        None => segments.last(),
     }
 }
 
@@ -107,6 +695,7 @@ where
     segment: S,
     // Some(x) means we are looking for the xth segment, None means check last segment of key
     at: Option<usize>,
+    separator: char,
     underlying: I,
 }
 
@@ -120,9 +709,18 @@ where
         Self {
             segment,
             at,
+            separator: ':',
             underlying,
         }
     }
+
+    /// Extracts the compared segment using the separator of `format` instead of `:`.
+    pub fn with_format(self, format: KeyFormat) -> Self {
+        Self {
+            separator: format.separator,
+            ..self
+        }
+    }
 }
 
 impl<I, S> Iterator for SegmentAtEq<I, S>
@@ -135,10 +733,7 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(x) = self.underlying.next() {
-            let part = match self.at {
-                Some(at) => segment_at(x.get_key(), at),
-                None => last_segment(x.get_key()),
-            };
+            let part = compared_segment(x.get_key(), self.at, self.separator);
 
             if self.segment.as_ref() == part {
                 return Some(x);
@@ -157,6 +752,7 @@ where
     segments: V,
     // Some(x) means we are looking for the xth segment, None means check last segment of key
     at: Option<usize>,
+    separator: char,
     underlying: I,
     phantom: std::marker::PhantomData<S>,
 }
@@ -172,10 +768,19 @@ where
         Self {
             segments,
             at,
+            separator: ':',
             underlying,
             phantom: std::marker::PhantomData,
         }
     }
+
+    /// Extracts the compared segment using the separator of `format` instead of `:`.
+    pub fn with_format(self, format: KeyFormat) -> Self {
+        Self {
+            separator: format.separator,
+            ..self
+        }
+    }
 }
 
 impl<I, S, V> Iterator for SegmentAtIn<I, S, V>
@@ -193,10 +798,7 @@ where
         }
 
         while let Some(x) = self.underlying.next() {
-            let part = match self.at {
-                Some(at) => segment_at(x.get_key(), at),
-                None => last_segment(x.get_key()),
-            };
+            let part = compared_segment(x.get_key(), self.at, self.separator);
 
             if self.segments.as_ref().iter().any(|x| x.as_ref() == part) {
                 return Some(x);
@@ -205,3 +807,304 @@ where
         None
     }
 }
+
+/// Parses the segment at `index` of `key` into `T`.
+pub fn try_parse_segment_at<T>(key: &str, index: usize) -> Result<T, KeyError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    KeyFormat::default().try_parse_segment_at(key, index)
+}
+
+fn parse_segment<T>(key: &str, index: usize, segment: &str) -> Result<T, KeyError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(segment).map_err(|err| KeyError::InvalidSegment {
+        key: key.to_string(),
+        index,
+        segment: segment.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// `FromKeyParts` is implemented for tuples of up to 8 [FromStr] elements, the key must
+/// have exactly as many segments as the tuple has elements.
+pub trait FromKeyParts: Sized {
+    fn from_segments(segments: &Segments<'_>) -> Result<Self, KeyError>;
+
+    /// Parses a `:` separated key, use [KeyFormat::key_parts] for other separators.
+    fn from_key_parts(key: &str) -> Result<Self, KeyError> {
+        Self::from_segments(&Segments::new(key))
+    }
+}
+
+macro_rules! impl_from_key_parts {
+    ($count:expr => $($name:ident: $index:tt),+) => {
+        impl<$($name),+> FromKeyParts for ($($name,)+)
+        where
+            $($name: FromStr, $name::Err: Display,)+
+        {
+            fn from_segments(segments: &Segments<'_>) -> Result<Self, KeyError> {
+                let key = segments.key();
+                if segments.len() != $count {
+                    return Err(KeyError::SegmentCount {
+                        key: key.to_string(),
+                        expected: $count,
+                        actual: segments.len(),
+                    });
+                }
+
+                Ok(($(parse_segment::<$name>(
+                    key,
+                    $index,
+                    segments.get($index).expect("segment count checked above"),
+                )?,)+))
+            }
+        }
+    };
+}
+
+impl_from_key_parts!(1 => A: 0);
+impl_from_key_parts!(2 => A: 0, B: 1);
+impl_from_key_parts!(3 => A: 0, B: 1, C: 2);
+impl_from_key_parts!(4 => A: 0, B: 1, C: 2, D: 3);
+impl_from_key_parts!(5 => A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_from_key_parts!(6 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_from_key_parts!(7 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_from_key_parts!(8 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        segment_at_owned, try_parse_segment_at, Escaping, FromKeyParts, Key, KeyError, KeyFormat,
+        Segments,
+    };
+    use crate::scalar::BigInt;
+    use crate::Hex;
+
+    #[test]
+    fn parse_segment_at() {
+        assert_eq!(try_parse_segment_at::<u64>("day:19000", 1), Ok(19000));
+        assert_eq!(
+            try_parse_segment_at::<u64>("day", 1),
+            Err(KeyError::MissingSegment {
+                key: "day".to_string(),
+                index: 1
+            })
+        );
+        assert_eq!(
+            try_parse_segment_at::<u64>("day:abc", 1),
+            Err(KeyError::InvalidSegment {
+                key: "day:abc".to_string(),
+                index: 1,
+                segment: "abc".to_string(),
+                reason: "invalid digit found in string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn from_key_parts() {
+        let (kind, address, id) =
+            <(String, Hex<Vec<u8>>, u64)>::from_key_parts("pool:0x01ab:42").unwrap();
+        assert_eq!(kind, "pool");
+        assert_eq!(address.0, vec![0x01u8, 0xab]);
+        assert_eq!(id, 42);
+
+        assert_eq!(
+            <(String, u64)>::from_key_parts("pool:0x01ab:42"),
+            Err(KeyError::SegmentCount {
+                key: "pool:0x01ab:42".to_string(),
+                expected: 2,
+                actual: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn key_builder_round_trip() {
+        for escaping in [Escaping::Percent, Escaping::Backslash] {
+            let key = Key::with_escaping(escaping)
+                .push("pool")
+                .push_hex([0x01u8, 0xab])
+                .push(BigInt::from(-7))
+                .push("a:b%c\\d")
+                .push("");
+
+            let parsed = Key::parse_with_escaping(&key.to_string(), escaping).unwrap();
+            assert_eq!(parsed.segments(), &["pool", "01ab", "-7", "a:b%c\\d", ""]);
+            assert_eq!(parsed.segment::<i64>(2), Ok(-7));
+            assert_eq!(parsed, key);
+        }
+    }
+
+    #[test]
+    fn key_builder_escaping() {
+        let key = |escaping| Key::with_escaping(escaping).push("a:b").push(1).to_string();
+
+        assert_eq!(key(Escaping::None), "a:b:1");
+        assert_eq!(key(Escaping::Percent), "a%3Ab:1");
+        assert_eq!(key(Escaping::Backslash), "a\\:b:1");
+        assert_eq!(String::from(Key::new().push("user").push(1)), "user:1");
+    }
+
+    #[test]
+    fn key_empty() {
+        assert_eq!(Key::new().to_string(), "");
+        assert!(Key::new().is_empty());
+
+        let single = Key::new().push("");
+        assert_eq!(single.to_string(), "");
+        assert_eq!(Key::parse(""), Ok(single));
+        for escaping in [Escaping::None, Escaping::Percent, Escaping::Backslash] {
+            let parsed = Key::parse_with_escaping("", escaping).unwrap();
+            assert_eq!(parsed.segments(), &[""]);
+        }
+        assert_eq!(Key::parse(":").unwrap().segments(), &["", ""]);
+        assert_eq!(
+            Key::new().segment::<u64>(0),
+            Err(KeyError::MissingSegment {
+                key: "".to_string(),
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn key_parse_invalid_escape() {
+        assert_eq!(
+            Key::parse("pool:a%2"),
+            Err(KeyError::InvalidEscape {
+                key: "pool:a%2".to_string(),
+                position: 6
+            })
+        );
+        // Escapes `Key` never builds are rejected, lowercase or not `%` nor the separator
+        for (key, position) in [("pool:%61", 5), ("a%3a", 1), ("a%25%2F", 4), ("%3A%", 3)] {
+            assert_eq!(
+                Key::parse(key),
+                Err(KeyError::InvalidEscape {
+                    key: key.to_string(),
+                    position
+                })
+            );
+        }
+        let format = KeyFormat::new('é');
+        assert_eq!(format.parse("a%C3%A9b%25").unwrap().segments(), &["aéb%"]);
+        assert!(format.parse("a%C3").is_err());
+        assert_eq!(
+            Key::parse_with_escaping("pool:a\\b", Escaping::Backslash),
+            Err(KeyError::InvalidEscape {
+                key: "pool:a\\b".to_string(),
+                position: 6
+            })
+        );
+        assert_eq!(
+            Key::parse_with_escaping("a:b", Escaping::None)
+                .unwrap()
+                .segments(),
+            &["a", "b"]
+        );
+    }
+
+    #[test]
+    fn segments_access() {
+        let segments = Segments::new("a:bb::ccc");
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments.first(), "a");
+        assert_eq!(segments.get(1), Some("bb"));
+        assert_eq!(segments.get(2), Some(""));
+        assert_eq!(segments.last(), "ccc");
+        assert_eq!(segments.get(4), None);
+        assert_eq!(segments.get_back(0), Some("ccc"));
+        assert_eq!(segments.get_back(3), Some("a"));
+        assert_eq!(segments.get_back(4), None);
+
+        let single = Segments::new("");
+        assert_eq!(single.len(), 1);
+        assert_eq!(single.first(), "");
+        assert_eq!(single.last(), "");
+    }
+
+    #[test]
+    fn segments_spill_over_inline_capacity() {
+        let key = (0..20).map(|i| i.to_string()).collect::<Vec<_>>().join(":");
+        let segments = Segments::new(&key);
+
+        assert_eq!(segments.len(), 20);
+        assert_eq!(
+            segments.iter().collect::<Vec<_>>(),
+            key.split(':').collect::<Vec<_>>()
+        );
+        assert_eq!(
+            segments.iter().rev().collect::<Vec<_>>(),
+            key.rsplit(':').collect::<Vec<_>>()
+        );
+        assert_eq!(segments.get(7), Some("7"));
+        assert_eq!(segments.get(8), Some("8"));
+        assert_eq!(segments.get(19), Some("19"));
+    }
+
+    #[test]
+    fn segment_at_owned_extracts() {
+        assert_eq!(segment_at_owned("user:0x01:balance".to_string(), 1), "0x01");
+    }
+
+    #[test]
+    fn key_format_custom_separator() {
+        let format = KeyFormat::new('|');
+        let segments = format.segments("pool|0x01||day");
+        assert_eq!(
+            segments.iter().collect::<Vec<_>>(),
+            vec!["pool", "0x01", "", "day"]
+        );
+        assert_eq!(format.try_segment_at("pool|0x01", 2), None);
+        assert_eq!(format.first_segment("pool|0x01"), "pool");
+        assert_eq!(format.try_last_segment("pool|0x01"), Some("0x01"));
+        assert_eq!(format.try_parse_segment_at::<u64>("day|42", 1), Ok(42));
+        assert_eq!(
+            format.key_parts::<(String, u64)>("day|42"),
+            Ok(("day".to_string(), 42))
+        );
+
+        let multi_byte = KeyFormat::new('→');
+        assert_eq!(multi_byte.segment_at("a→bc→d", 1), "bc");
+    }
+
+    #[test]
+    fn key_format_round_trip() {
+        for separator in ['/', '|', '→'] {
+            for escaping in [Escaping::Percent, Escaping::Backslash] {
+                let format = KeyFormat::new(separator).with_escaping(escaping);
+                let segment = format!("a{}b%c\\d:e", separator);
+                let key = format.key().push("pool").push(&segment);
+
+                let parsed = format.parse(&key.to_string()).unwrap();
+                assert_eq!(parsed.segments(), &["pool".to_string(), segment]);
+            }
+        }
+
+        assert_eq!(
+            KeyFormat::try_new('%', Escaping::Percent),
+            Err(KeyError::InvalidSeparator {
+                separator: '%',
+                escaping: Escaping::Percent
+            })
+        );
+        assert!(KeyFormat::try_new('\\', Escaping::Backslash).is_err());
+        assert!(KeyFormat::try_new('%', Escaping::Backslash).is_ok());
+        assert!(KeyFormat::try_new('\\', Escaping::None).is_ok());
+
+        assert_eq!(
+            KeyFormat::new('/').key().push("a/b:c").to_string(),
+            "a%2Fb:c"
+        );
+        assert_eq!(
+            KeyFormat::new('→').key().push("a→b").to_string(),
+            "a%E2%86%92b"
+        );
+    }
+}
@@ -108,13 +108,16 @@
 //!```
 extern crate core;
 
+pub mod clock;
 pub mod errors;
 
 mod externs;
 pub mod handlers;
 mod hex;
+pub mod index;
 pub mod log;
 pub mod memory;
+pub mod params;
 
 /// Protobuf generated Substreams models
 pub mod pb;
@@ -142,16 +145,17 @@ pub mod prelude {
     pub use crate::scalar::{BigDecimal, BigInt};
     pub use crate::store::{
         Appender, Delta, DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaBool, DeltaBytes,
-        DeltaFloat64, DeltaInt32, DeltaInt64, DeltaProto, DeltaString, Deltas, StoreAdd,
-        StoreAddBigDecimal, StoreAddBigInt, StoreAddFloat64, StoreAddInt64, StoreAppend,
-        StoreDelete, StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetFloat64, StoreGetInt64,
-        StoreGetProto, StoreGetRaw, StoreGetString, StoreMax, StoreMaxBigDecimal, StoreMaxBigInt,
-        StoreMaxFloat64, StoreMaxInt64, StoreMin, StoreMinBigDecimal, StoreMinBigInt,
-        StoreMinFloat64, StoreMinInt64, StoreNew, StoreSet, StoreSetBigDecimal, StoreSetBigInt,
-        StoreSetFloat64, StoreSetIfNotExists, StoreSetIfNotExistsBigDecimal,
-        StoreSetIfNotExistsBigInt, StoreSetIfNotExistsFloat64, StoreSetIfNotExistsInt64,
-        StoreSetIfNotExistsProto, StoreSetIfNotExistsRaw, StoreSetIfNotExistsString, StoreSetInt64,
-        StoreSetProto, StoreSetRaw, StoreSetString,
+        DeltaFloat64, DeltaInt32, DeltaInt64, DeltaProto, DeltaString, DeltaValue, Deltas,
+        LazyDelta, LazyDeltas, StoreAdd, StoreAddBigDecimal, StoreAddBigInt, StoreAddFloat64,
+        StoreAddInt64, StoreAppend, StoreDelete, StoreGet, StoreGetBigDecimal, StoreGetBigInt,
+        StoreGetFloat64, StoreGetInt64, StoreGetProto, StoreGetRaw, StoreGetString, StoreGetValue,
+        StoreMax, StoreMaxBigDecimal, StoreMaxBigInt, StoreMaxFloat64, StoreMaxInt64, StoreMin,
+        StoreMinBigDecimal, StoreMinBigInt, StoreMinFloat64, StoreMinInt64, StoreNew, StoreSet,
+        StoreSetBigDecimal, StoreSetBigInt, StoreSetFloat64, StoreSetIfNotExists,
+        StoreSetIfNotExistsBigDecimal, StoreSetIfNotExistsBigInt, StoreSetIfNotExistsFloat64,
+        StoreSetIfNotExistsInt64, StoreSetIfNotExistsProto, StoreSetIfNotExistsRaw,
+        StoreSetIfNotExistsString, StoreSetIfNotExistsValue, StoreSetInt64, StoreSetProto,
+        StoreSetRaw, StoreSetString, StoreSetValue,
     };
 }
 
@@ -0,0 +1,380 @@
+//! Module params helpers for Substreams.
+//!
+//! A module's params are received as a string, handlers can declare a `#[params]` argument
+//! of any type implementing [Params] to have them decoded once by the generated code, a
+//! malformed params string being reported to the host as an
+//! [ErrorKind::Params](crate::errors::ErrorKind::Params) error.
+//!
+//! [Params] is implemented for all [FromStr] types, for [QueryParams], a parsed
+//! `key=value&...` query string, and, with the `json` feature, for [Json].
+//!
+//! ```rust
+//! # mod eth { pub type Block = (); }
+//! # mod pb { pub type Custom = (); }
+//! use substreams::params::{Params, ParamsError, QueryParams};
+//!
+//! struct Filter {
+//!     min_value: u64,
+//!     contract: Option<String>,
+//! }
+//!
+//! impl Params for Filter {
+//!     fn parse_params(params: &str) -> Result<Self, ParamsError> {
+//!         let query = QueryParams::parse(params)?;
+//!
+//!         Ok(Filter {
+//!             min_value: query.required("min_value")?,
+//!             contract: query.get("contract")?,
+//!         })
+//!     }
+//! }
+//!
+//! #[substreams::handlers::map]
+//! fn map_filtered(#[params] filter: Filter, blk: eth::Block) -> pb::Custom {
+//!     unimplemented!("do something");
+//! }
+//!
+//! let filter = Filter::parse_params("min_value=10&contract=0xabc").unwrap();
+//! assert_eq!(filter.min_value, 10);
+//! assert_eq!(filter.contract.as_deref(), Some("0xabc"));
+//! ```
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParamsError {
+    #[error("params are not valid UTF-8 (valid up to byte {valid_up_to})")]
+    InvalidUtf8 { valid_up_to: usize },
+    #[error("invalid params {params:?}: {reason}")]
+    Invalid { params: String, reason: String },
+    #[error("invalid percent escape at byte {position} in params {params:?}")]
+    InvalidEscape { params: String, position: usize },
+    #[error("missing param {name:?} in params {params:?}")]
+    Missing { name: String, params: String },
+    #[error("invalid value {value:?} for param {name:?}: {reason}")]
+    InvalidValue {
+        name: String,
+        value: String,
+        reason: String,
+    },
+}
+
+/// Params is implemented by types that can be decoded from a module's params string.
+pub trait Params: Sized {
+    fn parse_params(params: &str) -> Result<Self, ParamsError>;
+}
+
+impl<T> Params for T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn parse_params(params: &str) -> Result<Self, ParamsError> {
+        params.parse().map_err(|err: T::Err| ParamsError::Invalid {
+            params: params.to_string(),
+            reason: err.to_string(),
+        })
+    }
+}
+
+/// Given a pointer to the params string, it will decode it into a [Params] type.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn decode_ptr<T: Params>(ptr: *mut u8, size: usize) -> Result<T, ParamsError> {
+    unsafe {
+        let input_data = Vec::from_raw_parts(ptr, size, size);
+        let params = std::str::from_utf8(&input_data)
+            .map_err(|err| ParamsError::InvalidUtf8 {
+                valid_up_to: err.valid_up_to(),
+            })
+            .and_then(T::parse_params);
+        std::mem::forget(input_data); // otherwise tries to free that memory at the end and crashes
+        params
+    }
+}
+
+/// QueryParams are params in the query string format, `key=value` pairs separated by `&`.
+///
+/// Keys and values are percent-decoded, `+` being decoded as a space, a pair without `=`
+/// has an empty value and empty pairs are ignored. A key may be repeated, [QueryParams::get]
+/// returning its first value and [QueryParams::get_all] all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    params: String,
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    pub fn parse(params: &str) -> Result<Self, ParamsError> {
+        let mut pairs = Vec::new();
+        let mut offset = 0;
+
+        for pair in params.split('&') {
+            if !pair.is_empty() {
+                let (key, value) = match pair.find('=') {
+                    Some(at) => (&pair[..at], &pair[at + 1..]),
+                    None => (pair, ""),
+                };
+                let value_offset = offset + key.len() + 1;
+
+                pairs.push((
+                    query_decode(params, key, offset)?,
+                    query_decode(params, value, value_offset)?,
+                ));
+            }
+
+            offset += pair.len() + 1;
+        }
+
+        Ok(QueryParams {
+            params: params.to_string(),
+            pairs,
+        })
+    }
+
+    /// Returns the first raw value of param `name`.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all the raw values of param `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parses the first value of param `name`, returning `None` if it's not present.
+    pub fn get<T>(&self, name: &str) -> Result<Option<T>, ParamsError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get_str(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|err: T::Err| ParamsError::InvalidValue {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                        reason: err.to_string(),
+                    })
+            })
+            .transpose()
+    }
+
+    /// Parses the first value of param `name`, returning an error if it's not present.
+    pub fn required<T>(&self, name: &str) -> Result<T, ParamsError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(name)?.ok_or_else(|| ParamsError::Missing {
+            name: name.to_string(),
+            params: self.params.clone(),
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get_str(name).is_some()
+    }
+
+    /// Iterates over the decoded `(key, value)` pairs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl Params for QueryParams {
+    fn parse_params(params: &str) -> Result<Self, ParamsError> {
+        QueryParams::parse(params)
+    }
+}
+
+fn query_decode(params: &str, part: &str, offset: usize) -> Result<String, ParamsError> {
+    percent_decode(&part.replace('+', " ")).map_err(|position| ParamsError::InvalidEscape {
+        params: params.to_string(),
+        position: offset + position,
+    })
+}
+
+// Decodes `%XX` sequences, returns the byte position in `segment` of the first invalid
+// sequence, or of the first sequence not decoding to valid UTF-8, on error
+fn percent_decode(segment: &str) -> Result<String, usize> {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    // Position in `segment` of each decoded byte
+    let mut positions = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        positions.push(i);
+        if bytes[i] != b'%' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let code = segment
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.bytes().all(|x| x.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or(i)?;
+        out.push(code);
+        i += 3;
+    }
+
+    String::from_utf8(out).map_err(|err| positions[err.utf8_error().valid_up_to()])
+}
+
+/// Json decodes params in the JSON format into `T`.
+///
+/// ```rust
+/// use substreams::params::{Json, Params};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Filter {
+///     contracts: Vec<String>,
+/// }
+///
+/// let filter = Json::<Filter>::parse_params(r#"{"contracts": ["0xabc"]}"#).unwrap();
+/// assert_eq!(filter.contracts, vec!["0xabc"]);
+/// ```
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl<T> Json<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> std::ops::Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::de::DeserializeOwned> Params for Json<T> {
+    fn parse_params(params: &str) -> Result<Self, ParamsError> {
+        serde_json::from_str(params)
+            .map(Json)
+            .map_err(|err| ParamsError::Invalid {
+                params: params.to_string(),
+                reason: err.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Params, ParamsError, QueryParams};
+
+    #[test]
+    fn params_from_str() {
+        assert_eq!(u64::parse_params("42"), Ok(42));
+        assert_eq!(String::parse_params("a=b"), Ok("a=b".to_string()));
+        assert!(matches!(
+            u64::parse_params("x"),
+            Err(ParamsError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn query_params_parse() {
+        let query = QueryParams::parse("a=1&b=hello+world&&c&a=2&d=%3A%26").unwrap();
+
+        assert_eq!(query.len(), 5);
+        assert_eq!(query.get_str("a"), Some("1"));
+        assert_eq!(query.get_all("a").collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(query.get_str("b"), Some("hello world"));
+        assert_eq!(query.get_str("c"), Some(""));
+        assert_eq!(query.get_str("d"), Some(":&"));
+        assert!(query.contains("c"));
+        assert!(!query.contains("e"));
+        assert!(QueryParams::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn query_params_typed() {
+        let query = QueryParams::parse("min=10&max=abc").unwrap();
+
+        assert_eq!(query.get::<u64>("min"), Ok(Some(10)));
+        assert_eq!(query.get::<u64>("other"), Ok(None));
+        assert_eq!(query.required::<u64>("min"), Ok(10));
+        assert_eq!(
+            query.required::<u64>("other"),
+            Err(ParamsError::Missing {
+                name: "other".to_string(),
+                params: "min=10&max=abc".to_string(),
+            })
+        );
+        assert_eq!(
+            query.get::<u64>("max"),
+            Err(ParamsError::InvalidValue {
+                name: "max".to_string(),
+                value: "abc".to_string(),
+                reason: "invalid digit found in string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn query_params_invalid_escape() {
+        assert_eq!(
+            QueryParams::parse("a=1&b=%zz"),
+            Err(ParamsError::InvalidEscape {
+                params: "a=1&b=%zz".to_string(),
+                position: 6,
+            })
+        );
+        // The position of invalid UTF-8 is the one of its escape in the params
+        assert_eq!(
+            QueryParams::parse("a=1&b=xyz%C3%A9%FF"),
+            Err(ParamsError::InvalidEscape {
+                params: "a=1&b=xyz%C3%A9%FF".to_string(),
+                position: 15,
+            })
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_params() {
+        use super::Json;
+
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Filter {
+            min: u64,
+        }
+
+        assert_eq!(
+            Json::<Filter>::parse_params(r#"{"min": 3}"#).map(Json::into_inner),
+            Ok(Filter { min: 3 })
+        );
+        assert!(matches!(
+            Json::<Filter>::parse_params("min=3"),
+            Err(ParamsError::Invalid { .. })
+        ));
+    }
+}
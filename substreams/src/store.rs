@@ -60,15 +60,22 @@ use std::{io::BufRead, str};
 
 use crate::{key, operation, pb::substreams::store_delta::Operation};
 
+use anyhow::{anyhow, Context};
+use thiserror::Error;
+
 use {
     crate::{
+        errors::Error,
         pb::substreams::StoreDelta,
         scalar::{BigDecimal, BigInt},
         state, {pb, proto},
     },
     prost,
+    std::convert::TryFrom,
+    std::fmt::{self, Display},
     std::i64,
     std::marker::PhantomData,
+    std::ops::{Bound, RangeBounds},
     std::str::FromStr,
 };
 
@@ -92,6 +99,103 @@ pub trait StoreNew {
     fn new() -> Self;
 }
 
+/// `StoreValue` is the codec turning a value into the bytes saved in a `store` and back.
+///
+/// It is implemented for the built-in value types (`Vec<u8>`, `String`, `i64`, `f64`, [BigInt]
+/// and [BigDecimal]) using the same encoding as the typed stores. Implement it on your own type
+/// (packed struct, `u128` balance, etc.) to use it through the generic [StoreSetValue],
+/// [StoreSetIfNotExistsValue], [StoreGetValue] and [DeltaValue] wrappers:
+///
+/// ```rust
+/// use std::convert::TryInto;
+/// use substreams::errors::Error;
+/// use substreams::prelude::{StoreGet, StoreNew};
+/// use substreams::store::{StoreGetValue, StoreSetValue, StoreValue};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// pub struct Balance(u128);
+///
+/// impl StoreValue for Balance {
+///     fn encode(&self) -> Vec<u8> {
+///         self.0.to_be_bytes().to_vec()
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Result<Self, Error> {
+///         // The old value of a `Create` delta and the new value of a `Delete` delta are empty.
+///         if bytes.is_empty() {
+///             return Ok(Balance(0));
+///         }
+///
+///         let raw: [u8; 16] = bytes.try_into()?;
+///         Ok(Balance(u128::from_be_bytes(raw)))
+///     }
+/// }
+///
+/// #[substreams::handlers::store]
+/// fn store_balances(balances: StoreGetValue<Balance>, output: StoreSetValue<Balance>) {
+///     // do something
+/// }
+/// ```
+pub trait StoreValue: Sized {
+    /// Encodes the value into the bytes saved in the store.
+    fn encode(&self) -> Vec<u8>;
+    /// Decodes a value previously encoded with [StoreValue::encode].
+    fn decode(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+impl StoreValue for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl StoreValue for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(str::from_utf8(bytes)
+            .context("invalid UTF-8 sequence in store value")?
+            .to_string())
+    }
+}
+
+macro_rules! impl_store_value_from_str {
+    ($name:ty) => {
+        impl StoreValue for $name {
+            fn encode(&self) -> Vec<u8> {
+                self.to_string().into_bytes()
+            }
+
+            fn decode(bytes: &[u8]) -> Result<Self, Error> {
+                if bytes.is_empty() {
+                    return Ok(<$name>::default());
+                }
+
+                let as_str =
+                    str::from_utf8(bytes).context("invalid UTF-8 sequence in store value")?;
+                <$name>::from_str(as_str).map_err(|_| {
+                    anyhow!(
+                        "value {} is not a valid representation of {}",
+                        as_str,
+                        stringify!($name)
+                    )
+                })
+            }
+        }
+    };
+}
+
+impl_store_value_from_str!(i64);
+impl_store_value_from_str!(f64);
+impl_store_value_from_str!(BigInt);
+impl_store_value_from_str!(BigDecimal);
+
 /// `StoreSetRaw` is a struct representing a `store` with `updatePolicy` equal to `set` on a `valueType` equal to `bytes`
 ///     `StoreSetRaw` implements AsRef<[u8]> to give the client the flexibility
 ///     to either use the API with &Vec[...] or Vec[...].
@@ -283,6 +387,37 @@ impl<V: Default + prost::Message> StoreSet<V> for StoreSetProto<V> {
     }
 }
 
+/// `StoreSetValue` is a struct representing a `store` with `updatePolicy` equal to `set` where values
+/// are encoded through the [StoreValue] implementation of `V`
+#[allow(dead_code)]
+pub struct StoreSetValue<V: StoreValue> {
+    casper: PhantomData<V>,
+}
+
+impl<V: StoreValue> StoreDelete for StoreSetValue<V> {}
+
+impl<V: StoreValue> StoreNew for StoreSetValue<V> {
+    fn new() -> Self {
+        Self {
+            casper: PhantomData,
+        }
+    }
+}
+
+impl<V: StoreValue> StoreSet<V> for StoreSetValue<V> {
+    fn set<K: AsRef<str>>(&self, ord: u64, key: K, value: &V) {
+        state::set(ord as i64, key, value.encode())
+    }
+
+    fn set_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: &V) {
+        let bytes = value.encode();
+
+        for key in keys {
+            state::set(ord as i64, key, &bytes)
+        }
+    }
+}
+
 /// `StoreSetIfNotExists` is a trait which is implemented on any type of typed StoreSetIfNotExists
 pub trait StoreSetIfNotExists<V>: StoreDelete + StoreNew {
     /// Set a given key to a given value, if the key existed before, it will be ignored and not set.
@@ -480,6 +615,37 @@ impl<V: Default + prost::Message> StoreSetIfNotExists<V> for StoreSetIfNotExists
     }
 }
 
+/// `StoreSetIfNotExistsValue` is a struct representing a `store` module with `updatePolicy` equal to `set_if_not_exists`
+/// where values are encoded through the [StoreValue] implementation of `V`
+#[allow(dead_code)]
+pub struct StoreSetIfNotExistsValue<V: StoreValue> {
+    casper: PhantomData<V>,
+}
+
+impl<V: StoreValue> StoreNew for StoreSetIfNotExistsValue<V> {
+    fn new() -> Self {
+        Self {
+            casper: PhantomData,
+        }
+    }
+}
+
+impl<V: StoreValue> StoreDelete for StoreSetIfNotExistsValue<V> {}
+
+impl<V: StoreValue> StoreSetIfNotExists<V> for StoreSetIfNotExistsValue<V> {
+    fn set_if_not_exists<K: AsRef<str>>(&self, ord: u64, key: K, value: &V) {
+        state::set_if_not_exists(ord as i64, key, value.encode());
+    }
+
+    fn set_if_not_exists_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: &V) {
+        let bytes = value.encode();
+
+        for key in keys {
+            state::set_if_not_exists(ord as i64, key, &bytes);
+        }
+    }
+}
+
 /// `StoreAdd` is a trait which is implemented on any type of types StoreAdd
 pub trait StoreAdd<V>: StoreDelete + StoreNew {
     /// Add a given value to an already existing key
@@ -907,19 +1073,19 @@ impl StoreGet<i64> for StoreGetInt64 {
 
     fn get_at<K: AsRef<str>>(&self, ord: u64, key: K) -> Option<i64> {
         state::get_at(self.0.idx, ord as i64, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_i64)
     }
 
     fn get_last<K: AsRef<str>>(&self, key: K) -> Option<i64> {
         state::get_last(self.0.idx, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_i64)
     }
 
     fn get_first<K: AsRef<str>>(&self, key: K) -> Option<i64> {
         state::get_first(self.0.idx, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_i64)
     }
 
@@ -946,19 +1112,19 @@ impl StoreGet<f64> for StoreGetFloat64 {
 
     fn get_at<K: AsRef<str>>(&self, ord: u64, key: K) -> Option<f64> {
         state::get_at(self.0.idx, ord as i64, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_f64)
     }
 
     fn get_last<K: AsRef<str>>(&self, key: K) -> Option<f64> {
         state::get_last(self.0.idx, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_f64)
     }
 
     fn get_first<K: AsRef<str>>(&self, key: K) -> Option<f64> {
         state::get_first(self.0.idx, key)
-            .as_ref()
+            .as_deref()
             .map(decode_bytes_to_f64)
     }
 
@@ -1081,20 +1247,26 @@ impl<T: Into<String> + From<String>> StoreGet<Vec<T>> for StoreGetArray<T> {
 }
 
 fn split_array<T: Into<String> + From<String>>(bytes: Vec<u8>) -> Option<Vec<T>> {
+    try_split_array(bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn try_split_array<T: Into<String> + From<String>>(
+    bytes: Vec<u8>,
+) -> Result<Option<Vec<T>>, String> {
     let parts = std::io::Cursor::new(bytes).split(b';');
-    let chunks: Vec<_> = parts
+    let chunks = parts
         .map(|x| x.expect("Cursor is infallible"))
-        .filter(|x| x.len() > 0)
+        .filter(|x| !x.is_empty())
         .map(|part| {
             String::from_utf8(part)
-                .unwrap_or_else(|_| panic!("Invalid UTF-8 sequence in store value"))
-                .into()
+                .map(Into::into)
+                .map_err(|_| "Invalid UTF-8 sequence in store value".to_string())
         })
-        .collect();
+        .collect::<Result<Vec<T>, String>>()?;
 
     match chunks.len() {
-        0 => None,
-        _ => Some(chunks),
+        0 => Ok(None),
+        _ => Ok(Some(chunks)),
     }
 }
 
@@ -1154,9 +1326,101 @@ where
     }
 }
 
+/// `StoreGetValue` is a struct representing a read only store `store` where values are decoded
+/// through the [StoreValue] implementation of `V`
+#[allow(dead_code)]
+pub struct StoreGetValue<V> {
+    store: StoreGetRaw,
+    casper: PhantomData<V>,
+}
+
+impl<V: StoreValue> StoreGet<V> for StoreGetValue<V> {
+    fn new(idx: u32) -> Self {
+        Self {
+            store: StoreGetRaw { idx },
+            casper: PhantomData,
+        }
+    }
+
+    fn get_at<K: AsRef<str>>(&self, ord: u64, key: K) -> Option<V> {
+        let key_ref = key.as_ref();
+
+        self.store
+            .get_at(ord, key_ref)
+            .map(|bytes| decode_store_value(key_ref, &bytes))
+    }
+
+    fn get_last<K: AsRef<str>>(&self, key: K) -> Option<V> {
+        let key_ref = key.as_ref();
+
+        self.store
+            .get_last(key_ref)
+            .map(|bytes| decode_store_value(key_ref, &bytes))
+    }
+
+    fn get_first<K: AsRef<str>>(&self, key: K) -> Option<V> {
+        let key_ref = key.as_ref();
+
+        self.store
+            .get_first(key_ref)
+            .map(|bytes| decode_store_value(key_ref, &bytes))
+    }
+
+    fn has_at<K: AsRef<str>>(&self, ord: u64, key: K) -> bool {
+        self.store.has_at(ord, key)
+    }
+
+    fn has_last<K: AsRef<str>>(&self, key: K) -> bool {
+        self.store.has_last(key)
+    }
+
+    fn has_first<K: AsRef<str>>(&self, key: K) -> bool {
+        self.store.has_first(key)
+    }
+}
+
+fn decode_store_value<V: StoreValue>(key: &str, bytes: &[u8]) -> V {
+    V::decode(bytes)
+        .unwrap_or_else(|err| panic!("Unable to decode store value for key {}: {:?}", key, err))
+}
+
 pub trait Delta: PartialEq {
     fn get_key(&self) -> &String;
     fn get_operation(&self) -> pb::substreams::store_delta::Operation;
+
+    /// Returns the delta's ordinal, used by [Deltas::merge], [Deltas::sort_by_ordinal] and the
+    /// ordinal slicing methods, so implementations must return the ordinal of the
+    /// [StoreDelta] they were built from.
+    fn get_ordinal(&self) -> u64;
+
+    /// Parses the key segment at `index` into `T`, see [key::try_parse_segment_at].
+    fn segment<T>(&self, index: usize) -> Result<T, key::KeyError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        key::try_parse_segment_at(self.get_key(), index)
+    }
+
+    /// Iterates over the `:` separated segments of the key.
+    fn segments(&self) -> std::str::Split<'_, char> {
+        self.get_key().split(':')
+    }
+
+    /// Parses the whole key into a tuple of typed segments, the key must have exactly
+    /// as many segments as the tuple has elements.
+    ///
+    /// ```rust
+    /// use substreams::store::{Delta, DeltaInt64};
+    /// # use substreams::pb::substreams::store_delta::Operation;
+    /// # let delta = DeltaInt64 { operation: Operation::Create, ordinal: 0, key: "pool:0x01:42".to_string(), old_value: 0, new_value: 1 };
+    ///
+    /// let (kind, pool, id): (String, String, u64) = delta.key_parts().unwrap();
+    /// assert_eq!(id, 42);
+    /// ```
+    fn key_parts<T: key::FromKeyParts>(&self) -> Result<T, key::KeyError> {
+        T::from_key_parts(self.get_key())
+    }
 }
 
 pub trait DeltaExt: Iterator {
@@ -1238,6 +1502,8 @@ pub struct Deltas<T: Delta> {
 }
 
 impl<T: Delta + From<StoreDelta>> Deltas<T> {
+    /// Converts the raw store deltas, panics if one of them cannot be converted,
+    /// see [Deltas::try_new] for a non-panicking version.
     pub fn new(store_deltas: Vec<StoreDelta>) -> Self {
         Deltas {
             deltas: store_deltas.into_iter().map(Into::into).collect(),
@@ -1255,6 +1521,275 @@ impl<T: Delta + From<StoreDelta>> Deltas<T> {
     }
 }
 
+impl<T: Delta + for<'a> TryFrom<&'a StoreDelta, Error = DeltaError>> Deltas<T> {
+    /// Non-panicking version of [Deltas::new], returns the first delta that cannot be
+    /// converted, for example because its operation is unknown to this version of the crate.
+    pub fn try_new(store_deltas: Vec<StoreDelta>) -> Result<Self, DeltaError> {
+        Ok(Deltas {
+            deltas: store_deltas
+                .iter()
+                .map(T::try_from)
+                .collect::<Result<Vec<T>, DeltaError>>()?,
+        })
+    }
+}
+
+impl<T: Delta> Deltas<T> {
+    /// Merges multiple delta streams (for example deltas of two different store inputs)
+    /// into a single one sorted by ordinal. Deltas sharing the same ordinal keep the
+    /// order in which the streams were given.
+    pub fn merge<I: IntoIterator<Item = Deltas<T>>>(streams: I) -> Self {
+        let mut merged = Deltas {
+            deltas: streams.into_iter().flat_map(|x| x.deltas).collect(),
+        };
+        merged.sort_by_ordinal();
+        merged
+    }
+
+    /// Sorts the deltas by ordinal, deltas sharing the same ordinal keep their relative order.
+    pub fn sort_by_ordinal(&mut self) {
+        self.deltas.sort_by_key(|x| x.get_ordinal());
+    }
+
+    /// Returns the deltas whose ordinal falls within `range`, for example all the deltas
+    /// that happened within a transaction with `deltas.ordinal_range(trx.begin_ordinal..trx.end_ordinal)`.
+    ///
+    /// The deltas must be sorted by ordinal, which is always the case for deltas received
+    /// from the engine or produced by [Deltas::merge].
+    pub fn ordinal_range<R: RangeBounds<u64>>(&self, range: R) -> &[T] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.deltas.partition_point(|x| x.get_ordinal() < *start),
+            Bound::Excluded(start) => self.deltas.partition_point(|x| x.get_ordinal() <= *start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.deltas.partition_point(|x| x.get_ordinal() <= *end),
+            Bound::Excluded(end) => self.deltas.partition_point(|x| x.get_ordinal() < *end),
+            Bound::Unbounded => self.deltas.len(),
+        };
+
+        if start >= end {
+            return &[];
+        }
+
+        &self.deltas[start..end]
+    }
+
+    /// Iterates over `items` alongside the deltas contained in each item's ordinal span,
+    /// `span` being called on each item to retrieve its ordinal range. Typically used to
+    /// attribute store changes to the transaction that produced them:
+    ///
+    /// ```rust
+    /// use substreams::store::{DeltaBigInt, Deltas};
+    /// # struct Transaction { hash: Vec<u8>, begin_ordinal: u64, end_ordinal: u64 }
+    ///
+    /// fn balances_by_trx(transactions: Vec<Transaction>, deltas: Deltas<DeltaBigInt>) {
+    ///     for (trx, trx_deltas) in deltas.ordinal_spans(&transactions, |trx| trx.begin_ordinal..trx.end_ordinal) {
+    ///         // Do something with the balance changes `trx_deltas` that happened within `trx`
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Like [Deltas::ordinal_range], the deltas must be sorted by ordinal.
+    pub fn ordinal_spans<'a, I, F, R>(
+        &'a self,
+        items: I,
+        span: F,
+    ) -> impl Iterator<Item = (I::Item, &'a [T])> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        F: Fn(&I::Item) -> R + 'a,
+        R: RangeBounds<u64>,
+    {
+        items.into_iter().map(move |item| {
+            let deltas = self.ordinal_range(span(&item));
+            (item, deltas)
+        })
+    }
+}
+
+/// `LazyDeltas` is the lazy counterpart of [Deltas], it keeps the raw [StoreDelta] received
+/// from the engine and only decodes the old and new values of a delta when they are accessed.
+///
+/// Filtering through [DeltaExt] works directly on the borrowed [LazyDelta] items, so deltas
+/// that are filtered out never pay the cost of parsing their values:
+///
+/// ```rust
+/// use substreams::scalar::BigDecimal;
+/// use substreams::store::{DeltaExt, LazyDeltas};
+///
+/// fn db_out(deltas: LazyDeltas<BigDecimal>) {
+///     for delta in deltas.iter().key_first_segment_eq("user") {
+///         let volume = delta.new_value();
+///         // Do something with the `user:<address>` volume
+///     }
+/// }
+/// ```
+pub struct LazyDeltas<V> {
+    pub deltas: Vec<StoreDelta>,
+    casper: PhantomData<V>,
+}
+
+// Implemented by hand like for `LazyDelta`, `V` only tells how to decode the raw deltas.
+impl<V> fmt::Debug for LazyDeltas<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyDeltas")
+            .field("deltas", &self.deltas)
+            .finish()
+    }
+}
+
+impl<V> Clone for LazyDeltas<V> {
+    fn clone(&self) -> Self {
+        LazyDeltas {
+            deltas: self.deltas.clone(),
+            casper: PhantomData,
+        }
+    }
+}
+
+impl<V> PartialEq for LazyDeltas<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deltas == other.deltas
+    }
+}
+
+impl<V: StoreValue> LazyDeltas<V> {
+    pub fn new(store_deltas: Vec<StoreDelta>) -> Self {
+        LazyDeltas {
+            deltas: store_deltas,
+            casper: PhantomData,
+        }
+    }
+
+    /// Iterates over the deltas without decoding any value.
+    pub fn iter(&self) -> impl Iterator<Item = LazyDelta<'_, V>> {
+        self.deltas.iter().map(LazyDelta::new)
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+}
+
+/// `LazyDelta` is a borrowed view over a raw [StoreDelta], values are decoded through
+/// the [StoreValue] implementation of `V` only when requested.
+pub struct LazyDelta<'a, V> {
+    raw: &'a StoreDelta,
+    casper: PhantomData<V>,
+}
+
+// Implemented by hand, deriving them would require `V` itself to be `Debug`, `Clone`, `Copy`
+// and `PartialEq` while only the borrowed delta is printed, copied and compared.
+impl<'a, V> fmt::Debug for LazyDelta<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyDelta").field("raw", &self.raw).finish()
+    }
+}
+
+impl<'a, V> Clone for LazyDelta<'a, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, V> Copy for LazyDelta<'a, V> {}
+
+impl<'a, V> PartialEq for LazyDelta<'a, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<'a, V: StoreValue> LazyDelta<'a, V> {
+    fn new(raw: &'a StoreDelta) -> Self {
+        LazyDelta {
+            raw,
+            casper: PhantomData,
+        }
+    }
+
+    pub fn get_key(&self) -> &'a String {
+        &self.raw.key
+    }
+
+    pub fn get_operation(&self) -> pb::substreams::store_delta::Operation {
+        self.try_get_operation()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_get_operation(&self) -> Result<pb::substreams::store_delta::Operation, DeltaError> {
+        operation_of(self.raw)
+    }
+
+    pub fn get_ordinal(&self) -> u64 {
+        self.raw.ordinal
+    }
+
+    /// The old value bytes as received from the engine, without any decoding.
+    pub fn raw_old_value(&self) -> &'a [u8] {
+        &self.raw.old_value
+    }
+
+    /// The new value bytes as received from the engine, without any decoding.
+    pub fn raw_new_value(&self) -> &'a [u8] {
+        &self.raw.new_value
+    }
+
+    /// Decodes the old value, panics if the bytes are not a valid `V`.
+    pub fn old_value(&self) -> V {
+        self.try_old_value().unwrap_or_else(|err| {
+            panic!(
+                "Unable to decode Store LazyDelta old value for key {}: {:?}",
+                self.raw.key, err
+            )
+        })
+    }
+
+    /// Decodes the new value, panics if the bytes are not a valid `V`.
+    pub fn new_value(&self) -> V {
+        self.try_new_value().unwrap_or_else(|err| {
+            panic!(
+                "Unable to decode Store LazyDelta new value for key {}: {:?}",
+                self.raw.key, err
+            )
+        })
+    }
+
+    pub fn try_old_value(&self) -> Result<V, Error> {
+        V::decode(&self.raw.old_value)
+    }
+
+    pub fn try_new_value(&self) -> Result<V, Error> {
+        V::decode(&self.raw.new_value)
+    }
+
+    /// Decodes both values and returns the owned [DeltaValue].
+    pub fn to_delta(&self) -> DeltaValue<V>
+    where
+        V: PartialEq,
+    {
+        DeltaValue::try_from(self.raw).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<'a, V: StoreValue + PartialEq> Delta for LazyDelta<'a, V> {
+    fn get_key(&self) -> &String {
+        &self.raw.key
+    }
+    fn get_operation(&self) -> pb::substreams::store_delta::Operation {
+        LazyDelta::get_operation(self)
+    }
+    fn get_ordinal(&self) -> u64 {
+        self.raw.ordinal
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeltaBigDecimal {
     pub operation: pb::substreams::store_delta::Operation,
@@ -1264,15 +1799,17 @@ pub struct DeltaBigDecimal {
     pub new_value: BigDecimal,
 }
 
-impl From<StoreDelta> for DeltaBigDecimal {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaBigDecimal {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: BigDecimal::from_store_bytes(&d.old_value),
-            new_value: BigDecimal::from_store_bytes(&d.new_value),
-        }
+            old_value: delta_value(&d.key, "old", BigDecimal::decode(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", BigDecimal::decode(&d.new_value))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1285,15 +1822,17 @@ pub struct DeltaBigInt {
     pub new_value: BigInt,
 }
 
-impl From<StoreDelta> for DeltaBigInt {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaBigInt {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: BigInt::from_store_bytes(&d.old_value),
-            new_value: BigInt::from_store_bytes(&d.new_value),
-        }
+            old_value: delta_value(&d.key, "old", BigInt::decode(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", BigInt::decode(&d.new_value))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1306,15 +1845,17 @@ pub struct DeltaInt32 {
     pub new_value: i32,
 }
 
-impl From<StoreDelta> for DeltaInt32 {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaInt32 {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: decode_bytes_to_i32(&d.old_value),
-            new_value: decode_bytes_to_i32(&d.new_value),
-        }
+            old_value: delta_value(&d.key, "old", try_decode_bytes_to_i32(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", try_decode_bytes_to_i32(&d.new_value))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1327,15 +1868,17 @@ pub struct DeltaInt64 {
     pub new_value: i64,
 }
 
-impl From<StoreDelta> for DeltaInt64 {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaInt64 {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: decode_bytes_to_i64(&d.old_value),
-            new_value: decode_bytes_to_i64(&d.new_value),
-        }
+            old_value: delta_value(&d.key, "old", try_decode_bytes_to_i64(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", try_decode_bytes_to_i64(&d.new_value))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1348,15 +1891,17 @@ pub struct DeltaFloat64 {
     pub new_value: f64,
 }
 
-impl From<StoreDelta> for DeltaFloat64 {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaFloat64 {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: decode_bytes_to_f64(&d.old_value),
-            new_value: decode_bytes_to_f64(&d.new_value),
-        }
+            old_value: delta_value(&d.key, "old", try_decode_bytes_to_f64(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", try_decode_bytes_to_f64(&d.new_value))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1369,15 +1914,17 @@ pub struct DeltaBool {
     pub new_value: bool,
 }
 
-impl From<StoreDelta> for DeltaBool {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaBool {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
             old_value: !d.old_value.contains(&0),
             new_value: !d.new_value.contains(&0),
-        }
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1390,15 +1937,17 @@ pub struct DeltaBytes {
     pub new_value: Vec<u8>,
 }
 
-impl From<StoreDelta> for DeltaBytes {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaBytes {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: d.old_value,
-            new_value: d.new_value,
-        }
+            key: d.key.clone(),
+            old_value: d.old_value.clone(),
+            new_value: d.new_value.clone(),
+        })
     }
 }
 
@@ -1411,19 +1960,17 @@ pub struct DeltaString {
     pub new_value: String,
 }
 
-impl From<StoreDelta> for DeltaString {
-    fn from(d: StoreDelta) -> Self {
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+impl TryFrom<&StoreDelta> for DeltaString {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: String::from_utf8(d.old_value).unwrap_or_else(|_| {
-                panic!("Invalid UTF-8 sequence in Store DeltaString old value")
-            }),
-            new_value: String::from_utf8(d.new_value).unwrap_or_else(|_| {
-                panic!("Invalid UTF-8 sequence in Store DeltaString new value")
-            }),
-        }
+            old_value: delta_value(&d.key, "old", String::from_utf8(d.old_value.clone()))?,
+            new_value: delta_value(&d.key, "new", String::from_utf8(d.new_value.clone()))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1436,20 +1983,17 @@ pub struct DeltaProto<T> {
     pub new_value: T,
 }
 
-impl<T: Default + prost::Message + PartialEq> From<StoreDelta> for DeltaProto<T> {
-    fn from(d: StoreDelta) -> Self {
-        let nv: T = prost::Message::decode(d.new_value.as_ref())
-            .unwrap_or_else(|_| panic!("Unable to decode Store DeltaProto for new value"));
-        let ov: T = prost::Message::decode(d.old_value.as_ref())
-            .unwrap_or_else(|_| panic!("Unable to decode Store DeltaProto for old value"));
+impl<T: Default + prost::Message + PartialEq> TryFrom<&StoreDelta> for DeltaProto<T> {
+    type Error = DeltaError;
 
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: ov,
-            new_value: nv,
-        }
+            old_value: delta_value(&d.key, "old", T::decode(d.old_value.as_ref()))?,
+            new_value: delta_value(&d.key, "new", T::decode(d.new_value.as_ref()))?,
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1460,6 +2004,9 @@ impl<T: Default + prost::Message + PartialEq> Delta for DeltaProto<T> {
     fn get_operation(&self) -> pb::substreams::store_delta::Operation {
         return self.operation;
     }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
 }
 
 impl<T: Default + prost::Message + PartialEq> Delta for &DeltaProto<T> {
@@ -1469,6 +2016,9 @@ impl<T: Default + prost::Message + PartialEq> Delta for &DeltaProto<T> {
     fn get_operation(&self) -> pb::substreams::store_delta::Operation {
         return self.operation;
     }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1480,18 +2030,19 @@ pub struct DeltaArray<T> {
     pub new_value: Vec<T>,
 }
 
-impl<T: Into<String> + From<String> + PartialEq> From<StoreDelta> for DeltaArray<T> {
-    fn from(d: StoreDelta) -> Self {
-        let old = split_array::<T>(d.old_value).unwrap_or_default();
-        let new = split_array::<T>(d.new_value).unwrap_or_default();
+impl<T: Into<String> + From<String> + PartialEq> TryFrom<&StoreDelta> for DeltaArray<T> {
+    type Error = DeltaError;
 
-        Self {
-            operation: convert_i32_to_operation(d.operation),
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
             ordinal: d.ordinal,
-            key: d.key,
-            old_value: old,
-            new_value: new,
-        }
+            old_value: delta_value(&d.key, "old", try_split_array::<T>(d.old_value.clone()))?
+                .unwrap_or_default(),
+            new_value: delta_value(&d.key, "new", try_split_array::<T>(d.new_value.clone()))?
+                .unwrap_or_default(),
+            key: d.key.clone(),
+        })
     }
 }
 
@@ -1502,6 +2053,9 @@ impl<T: Into<String> + From<String> + PartialEq> Delta for DeltaArray<T> {
     fn get_operation(&self) -> pb::substreams::store_delta::Operation {
         return self.operation;
     }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
 }
 
 impl<T: Into<String> + From<String> + PartialEq> Delta for &DeltaArray<T> {
@@ -1511,6 +2065,56 @@ impl<T: Into<String> + From<String> + PartialEq> Delta for &DeltaArray<T> {
     fn get_operation(&self) -> pb::substreams::store_delta::Operation {
         return self.operation;
     }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaValue<V> {
+    pub operation: pb::substreams::store_delta::Operation,
+    pub ordinal: u64,
+    pub key: String,
+    pub old_value: V,
+    pub new_value: V,
+}
+
+impl<V: StoreValue + PartialEq> TryFrom<&StoreDelta> for DeltaValue<V> {
+    type Error = DeltaError;
+
+    fn try_from(d: &StoreDelta) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation_of(d)?,
+            ordinal: d.ordinal,
+            old_value: delta_value(&d.key, "old", V::decode(&d.old_value))?,
+            new_value: delta_value(&d.key, "new", V::decode(&d.new_value))?,
+            key: d.key.clone(),
+        })
+    }
+}
+
+impl<V: StoreValue + PartialEq> Delta for DeltaValue<V> {
+    fn get_key(&self) -> &String {
+        &self.key
+    }
+    fn get_operation(&self) -> pb::substreams::store_delta::Operation {
+        self.operation
+    }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
+}
+
+impl<V: StoreValue + PartialEq> Delta for &DeltaValue<V> {
+    fn get_key(&self) -> &String {
+        &self.key
+    }
+    fn get_operation(&self) -> pb::substreams::store_delta::Operation {
+        self.operation
+    }
+    fn get_ordinal(&self) -> u64 {
+        self.ordinal
+    }
 }
 
 macro_rules! impl_delta_ref {
@@ -1522,6 +2126,9 @@ macro_rules! impl_delta_ref {
             fn get_operation(&self) -> pb::substreams::store_delta::Operation {
                 self.operation
             }
+            fn get_ordinal(&self) -> u64 {
+                self.ordinal
+            }
         }
     };
 }
@@ -1535,6 +2142,9 @@ macro_rules! impl_delta {
             fn get_operation(&self) -> pb::substreams::store_delta::Operation {
                 self.operation
             }
+            fn get_ordinal(&self) -> u64 {
+                self.ordinal
+            }
         }
         impl $name {
             pub fn get_key(&self) -> &String {
@@ -1543,6 +2153,9 @@ macro_rules! impl_delta {
             pub fn get_operation(&self) -> pb::substreams::store_delta::Operation {
                 self.operation
             }
+            pub fn get_ordinal(&self) -> u64 {
+                self.ordinal
+            }
         }
     };
 }
@@ -1565,64 +2178,146 @@ impl_delta_ref!(&DeltaBool);
 impl_delta_ref!(&DeltaBytes);
 impl_delta_ref!(&DeltaString);
 
-fn convert_i32_to_operation(operation: i32) -> pb::substreams::store_delta::Operation {
-    Operation::from_i32(operation).unwrap_or_else(|| panic!("unhandled operation: {}", operation))
+// `From<StoreDelta>` conversions panic on the deltas rejected by `TryFrom<&StoreDelta>`
+macro_rules! impl_from_store_delta {
+    ($name:ty) => {
+        impl From<StoreDelta> for $name {
+            fn from(d: StoreDelta) -> Self {
+                Self::try_from(&d).unwrap_or_else(|err| panic!("{}", err))
+            }
+        }
+    };
+}
+
+impl_from_store_delta!(DeltaBigDecimal);
+impl_from_store_delta!(DeltaBigInt);
+impl_from_store_delta!(DeltaInt32);
+impl_from_store_delta!(DeltaInt64);
+impl_from_store_delta!(DeltaFloat64);
+impl_from_store_delta!(DeltaBool);
+impl_from_store_delta!(DeltaBytes);
+impl_from_store_delta!(DeltaString);
+
+impl<T: Default + prost::Message + PartialEq> From<StoreDelta> for DeltaProto<T> {
+    fn from(d: StoreDelta) -> Self {
+        Self::try_from(&d).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<T: Into<String> + From<String> + PartialEq> From<StoreDelta> for DeltaArray<T> {
+    fn from(d: StoreDelta) -> Self {
+        Self::try_from(&d).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<V: StoreValue + PartialEq> From<StoreDelta> for DeltaValue<V> {
+    fn from(d: StoreDelta) -> Self {
+        Self::try_from(&d).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+/// `DeltaError` is returned when a raw [StoreDelta] cannot be converted into a typed delta.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DeltaError {
+    /// The operation code is not known by this version of the crate, most probably
+    /// sent by a newer engine.
+    #[error("delta for key {key} has unknown operation {operation}")]
+    UnknownOperation { key: String, operation: i32 },
+    /// The old or new value bytes cannot be decoded to the delta's value type.
+    #[error("delta for key {key} has an invalid {field} value: {reason}")]
+    InvalidValue {
+        key: String,
+        field: &'static str,
+        reason: String,
+    },
+}
+
+fn operation_of(d: &StoreDelta) -> Result<Operation, DeltaError> {
+    match Operation::from_i32(d.operation) {
+        Some(operation) => Ok(operation),
+        None => Err(DeltaError::UnknownOperation {
+            key: d.key.clone(),
+            operation: d.operation,
+        }),
+    }
 }
 
-// We accept &Vec<u8> instead of &[u8] because use internally and makes it easier to chain
-fn decode_bytes_to_i32(bytes: &Vec<u8>) -> i32 {
+fn delta_value<V, E: Display>(
+    key: &str,
+    field: &'static str,
+    value: Result<V, E>,
+) -> Result<V, DeltaError> {
+    value.map_err(|err| DeltaError::InvalidValue {
+        key: key.to_string(),
+        field,
+        reason: format!("{:#}", err),
+    })
+}
+
+#[cfg(test)]
+fn decode_bytes_to_i32(bytes: &[u8]) -> i32 {
+    try_decode_bytes_to_i32(bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn try_decode_bytes_to_i32(bytes: &[u8]) -> Result<i32, String> {
     if bytes.is_empty() {
-        return 0;
+        return Ok(0);
     }
 
     // FIXME: If we are ready to accept the fact that `bytes` is always valid UTF-8, we could even use
     //        the unsafe `from_utf8_unchecked` version, we would need first to measure the impact and
     //        better understand implication of an invalid UTF-8 &str with `from_str` call.
-    let int_as_str =
-        std::str::from_utf8(bytes).expect("received bytes expected to be valid UTF-8 string");
+    let int_as_str = std::str::from_utf8(bytes)
+        .map_err(|_| "received bytes expected to be valid UTF-8 string".to_string())?;
 
-    i32::from_str(int_as_str).unwrap_or_else(|_| {
-        panic!(
+    i32::from_str(int_as_str).map_err(|_| {
+        format!(
             "value {} is not a valid representation of an i32",
             int_as_str
         )
     })
 }
 
-// We accept &Vec<u8> instead of &[u8] because use internally and makes it easier to chain
-fn decode_bytes_to_i64(bytes: &Vec<u8>) -> i64 {
+fn decode_bytes_to_i64(bytes: &[u8]) -> i64 {
+    try_decode_bytes_to_i64(bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn try_decode_bytes_to_i64(bytes: &[u8]) -> Result<i64, String> {
     if bytes.is_empty() {
-        return 0;
+        return Ok(0);
     }
 
     // FIXME: If we are ready to accept the fact that `bytes` is always valid UTF-8, we could even use
     //        the unsafe `from_utf8_unchecked` version, we would need first to measure the impact and
     //        better understand implication of an invalid UTF-8 &str with `from_str` call.
-    let int_as_str =
-        std::str::from_utf8(bytes).expect("received bytes expected to be valid UTF-8 string");
+    let int_as_str = std::str::from_utf8(bytes)
+        .map_err(|_| "received bytes expected to be valid UTF-8 string".to_string())?;
 
-    i64::from_str(int_as_str).unwrap_or_else(|_| {
-        panic!(
+    i64::from_str(int_as_str).map_err(|_| {
+        format!(
             "value {} is not a valid representation of an i64",
             int_as_str
         )
     })
 }
 
-// We accept &Vec<u8> instead of &[u8] because use internally and makes it easier to chain
-fn decode_bytes_to_f64(bytes: &Vec<u8>) -> f64 {
+fn decode_bytes_to_f64(bytes: &[u8]) -> f64 {
+    try_decode_bytes_to_f64(bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn try_decode_bytes_to_f64(bytes: &[u8]) -> Result<f64, String> {
     if bytes.is_empty() {
-        return 0.0;
+        return Ok(0.0);
     }
 
     // FIXME: If we are ready to accept the fact that `bytes` is always valid UTF-8, we could even use
     //        the unsafe `from_utf8_unchecked` version, we would need first to measure the impact and
     //        better understand implication of an invalid UTF-8 &str with `from_str` call.
-    let float64_as_str =
-        std::str::from_utf8(bytes).expect("received bytes expected to be valid UTF-8 string");
+    let float64_as_str = std::str::from_utf8(bytes)
+        .map_err(|_| "received bytes expected to be valid UTF-8 string".to_string())?;
 
-    f64::from_str(float64_as_str).unwrap_or_else(|_| {
-        panic!(
+    f64::from_str(float64_as_str).map_err(|_| {
+        format!(
             "value {} is not a valid representation of an f64",
             float64_as_str
         )
@@ -1631,11 +2326,16 @@ fn decode_bytes_to_f64(bytes: &Vec<u8>) -> f64 {
 
 #[cfg(test)]
 mod tests {
+    use std::convert::{TryFrom, TryInto};
+    use std::ops::Bound;
+
     use crate::{
+        key::KeyFormat,
         pb::substreams::{store_delta::Operation, StoreDelta},
+        scalar::{BigDecimal, BigInt},
         store::{
             decode_bytes_to_f64, decode_bytes_to_i32, decode_bytes_to_i64, split_array, DeltaArray,
-            Deltas,
+            DeltaError, DeltaExt, DeltaInt64, DeltaValue, Deltas, LazyDeltas, StoreValue,
         },
     };
 
@@ -1770,4 +2470,347 @@ mod tests {
 
         assert_eq!(expected_value, actual_value)
     }
+
+    #[test]
+    fn store_value_builtins_round_trip() {
+        assert_eq!(i64::decode(&(-42i64).encode()).unwrap(), -42);
+        assert_eq!(f64::decode(&1.5f64.encode()).unwrap(), 1.5);
+        assert_eq!(
+            String::decode(&"value".to_string().encode()).unwrap(),
+            "value"
+        );
+        assert_eq!(
+            BigInt::decode(&BigInt::from(1234u64).encode()).unwrap(),
+            BigInt::from(1234u64)
+        );
+        assert_eq!(
+            BigDecimal::decode(&BigDecimal::from(12u64).encode()).unwrap(),
+            BigDecimal::from(12u64)
+        );
+    }
+
+    #[test]
+    fn store_value_empty_bytes_decode_to_default() {
+        assert_eq!(i64::decode(&[]).unwrap(), 0);
+        assert_eq!(BigInt::decode(&[]).unwrap(), BigInt::zero());
+    }
+
+    #[test]
+    fn store_value_invalid_bytes() {
+        assert!(i64::decode("invalid".as_bytes()).is_err());
+        assert!(String::decode(&[0xff, 0xfe]).is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Balance(u128);
+
+    impl StoreValue for Balance {
+        fn encode(&self) -> Vec<u8> {
+            self.0.to_be_bytes().to_vec()
+        }
+
+        fn decode(bytes: &[u8]) -> Result<Self, crate::errors::Error> {
+            if bytes.is_empty() {
+                return Ok(Balance(0));
+            }
+
+            let raw: [u8; 16] = bytes.try_into()?;
+            Ok(Balance(u128::from_be_bytes(raw)))
+        }
+    }
+
+    #[test]
+    fn delta_value_custom_type() {
+        let deltas = Deltas::<DeltaValue<Balance>>::new(vec![StoreDelta {
+            operation: 2,
+            ordinal: 3,
+            key: "balance:0x01".to_string(),
+            old_value: vec![],
+            new_value: Balance(u128::MAX).encode(),
+        }]);
+
+        assert_eq!(
+            Deltas::<DeltaValue<Balance>> {
+                deltas: vec![DeltaValue::<Balance> {
+                    operation: Operation::Update,
+                    ordinal: 3,
+                    key: "balance:0x01".to_string(),
+                    old_value: Balance(0),
+                    new_value: Balance(u128::MAX),
+                }]
+            },
+            deltas
+        );
+    }
+
+    #[test]
+    fn lazy_deltas_decode_on_access() {
+        let deltas = LazyDeltas::<BigDecimal>::new(vec![
+            StoreDelta {
+                operation: 1,
+                ordinal: 1,
+                key: "user:0x01".to_string(),
+                old_value: vec![],
+                new_value: "1.5".as_bytes().to_vec(),
+            },
+            StoreDelta {
+                operation: 1,
+                ordinal: 2,
+                key: "pool:0x02".to_string(),
+                old_value: vec![],
+                new_value: "not a decimal".as_bytes().to_vec(),
+            },
+        ]);
+
+        let users: Vec<_> = deltas.iter().key_first_segment_eq("user").collect();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].get_key(), "user:0x01");
+        assert_eq!(users[0].get_ordinal(), 1);
+        assert_eq!(users[0].old_value(), BigDecimal::zero());
+        assert_eq!(users[0].new_value(), "1.5".parse::<BigDecimal>().unwrap());
+
+        // `LazyDelta` is `Copy` even though `BigDecimal` is not.
+        let user = users[0];
+        assert_eq!(user, users[0]);
+
+        // `LazyDeltas` is `Clone`, `PartialEq` and `Debug` whatever its value type.
+        struct Opaque;
+        impl StoreValue for Opaque {
+            fn encode(&self) -> Vec<u8> {
+                vec![]
+            }
+
+            fn decode(_: &[u8]) -> Result<Self, crate::errors::Error> {
+                Ok(Opaque)
+            }
+        }
+        let opaque = LazyDeltas::<Opaque>::new(deltas.deltas.clone());
+        assert_eq!(opaque.clone(), opaque);
+        assert!(format!("{:?}", opaque).starts_with("LazyDeltas { deltas: ["));
+        assert!(format!("{:?}", opaque.iter().next().unwrap()).starts_with("LazyDelta { raw: "));
+
+        let pools: Vec<_> = deltas.iter().key_first_segment_eq("pool").collect();
+        assert_eq!(pools[0].raw_new_value(), "not a decimal".as_bytes());
+        assert!(pools[0].try_new_value().is_err());
+    }
+
+    #[test]
+    fn lazy_delta_to_delta() {
+        let deltas = LazyDeltas::<i64>::new(vec![StoreDelta {
+            operation: 2,
+            ordinal: 7,
+            key: "count".to_string(),
+            old_value: "1".as_bytes().to_vec(),
+            new_value: "2".as_bytes().to_vec(),
+        }]);
+
+        assert_eq!(
+            deltas.iter().next().unwrap().to_delta(),
+            DeltaValue {
+                operation: Operation::Update,
+                ordinal: 7,
+                key: "count".to_string(),
+                old_value: 1,
+                new_value: 2,
+            }
+        );
+    }
+
+    fn int64_delta(ordinal: u64, key: &str) -> DeltaInt64 {
+        DeltaInt64 {
+            operation: Operation::Update,
+            ordinal,
+            key: key.to_string(),
+            old_value: 0,
+            new_value: 1,
+        }
+    }
+
+    fn keys<'a>(deltas: impl IntoIterator<Item = &'a DeltaInt64>) -> Vec<&'a str> {
+        deltas.into_iter().map(|x| x.key.as_str()).collect()
+    }
+
+    #[test]
+    fn delta_ext_with_key_format() {
+        let deltas = vec![
+            int64_delta(1, "pool/0x01/day"),
+            int64_delta(2, "token/0x02/day"),
+            int64_delta(3, "pool/0x03/hour"),
+        ];
+
+        let pools: Vec<_> = deltas
+            .iter()
+            .key_first_segment_eq("pool")
+            .with_format(KeyFormat::new('/'))
+            .collect();
+        assert_eq!(keys(pools), vec!["pool/0x01/day", "pool/0x03/hour"]);
+
+        let days: Vec<_> = deltas
+            .iter()
+            .key_last_segment_in(["day"])
+            .with_format(KeyFormat::new('/'))
+            .collect();
+        assert_eq!(keys(days), vec!["pool/0x01/day", "token/0x02/day"]);
+    }
+
+    #[test]
+    fn deltas_merge_in_ordinal_order() {
+        let left = Deltas {
+            deltas: vec![int64_delta(1, "a1"), int64_delta(5, "a5")],
+        };
+        let right = Deltas {
+            deltas: vec![
+                int64_delta(1, "b1"),
+                int64_delta(3, "b3"),
+                int64_delta(9, "b9"),
+            ],
+        };
+
+        let merged = Deltas::merge(vec![left, right]);
+        assert_eq!(keys(&merged.deltas), vec!["a1", "b1", "b3", "a5", "b9"]);
+    }
+
+    #[test]
+    fn deltas_ordinal_range() {
+        let deltas = Deltas {
+            deltas: vec![
+                int64_delta(1, "d1"),
+                int64_delta(3, "d3"),
+                int64_delta(3, "d3bis"),
+                int64_delta(7, "d7"),
+            ],
+        };
+
+        assert_eq!(keys(deltas.ordinal_range(3..7)), vec!["d3", "d3bis"]);
+        assert_eq!(keys(deltas.ordinal_range(3..=7)), vec!["d3", "d3bis", "d7"]);
+        assert_eq!(keys(deltas.ordinal_range(..3)), vec!["d1"]);
+        assert_eq!(keys(deltas.ordinal_range(4..)), vec!["d7"]);
+        assert!(deltas.ordinal_range(4..7).is_empty());
+        assert!(deltas
+            .ordinal_range((Bound::Included(7), Bound::Excluded(3)))
+            .is_empty());
+    }
+
+    #[test]
+    fn deltas_ordinal_spans() {
+        let deltas = Deltas {
+            deltas: vec![
+                int64_delta(2, "d2"),
+                int64_delta(4, "d4"),
+                int64_delta(11, "d11"),
+            ],
+        };
+        let spans = vec![(0u64, 5u64), (5, 10), (10, 20)];
+
+        let grouped: Vec<_> = deltas
+            .ordinal_spans(&spans, |(begin, end)| *begin..*end)
+            .map(|(span, deltas)| (span.0, keys(deltas)))
+            .collect();
+
+        assert_eq!(
+            grouped,
+            vec![(0, vec!["d2", "d4"]), (5, vec![]), (10, vec!["d11"])]
+        );
+    }
+
+    fn raw_delta(operation: i32, new_value: &str) -> StoreDelta {
+        StoreDelta {
+            operation,
+            ordinal: 1,
+            key: "pool:0x01".to_string(),
+            old_value: vec![],
+            new_value: new_value.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn delta_try_from_unknown_operation() {
+        assert_eq!(
+            DeltaInt64::try_from(&raw_delta(42, "1")),
+            Err(DeltaError::UnknownOperation {
+                key: "pool:0x01".to_string(),
+                operation: 42,
+            })
+        );
+    }
+
+    #[test]
+    fn delta_from_unset_operation() {
+        let delta = DeltaInt64::from(raw_delta(Operation::Unset as i32, "1"));
+        assert_eq!(delta.operation, Operation::Unset);
+        assert_eq!(delta.new_value, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "delta for key pool:0x01 has unknown operation 42")]
+    fn delta_from_unknown_operation() {
+        DeltaInt64::from(raw_delta(42, "1"));
+    }
+
+    #[test]
+    fn delta_try_from_invalid_value() {
+        assert_eq!(
+            DeltaInt64::try_from(&raw_delta(Operation::Create as i32, "abc")),
+            Err(DeltaError::InvalidValue {
+                key: "pool:0x01".to_string(),
+                field: "new",
+                reason: "value abc is not a valid representation of an i64".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn deltas_try_new() {
+        let deltas =
+            Deltas::<DeltaInt64>::try_new(vec![raw_delta(Operation::Create as i32, "10")]).unwrap();
+        assert_eq!(deltas.deltas[0].new_value, 10);
+        assert_eq!(deltas.deltas[0].operation, Operation::Create);
+
+        let result = Deltas::<DeltaArray<String>>::try_new(vec![
+            raw_delta(Operation::Update as i32, "a;b"),
+            raw_delta(7, "c"),
+        ]);
+        assert!(matches!(
+            result,
+            Err(DeltaError::UnknownOperation { operation: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn deltas_input_skips_unknown_operations() {
+        use crate::handlers::HandlerInput;
+        use crate::pb::substreams::StoreDeltas;
+
+        // Deltas as passed by the host, the input not taking ownership of their bytes
+        let encode = |deltas| crate::proto::encode(&StoreDeltas { deltas }).unwrap();
+        let mut raw = encode(vec![
+            raw_delta(Operation::Create as i32, "10"),
+            raw_delta(42, "11"),
+            raw_delta(Operation::Unset as i32, "12"),
+        ]);
+
+        let deltas = Deltas::<DeltaInt64>::from_host(raw.as_mut_ptr(), raw.len()).unwrap();
+        let values: Vec<_> = deltas.iter().map(|d| d.new_value).collect();
+        assert_eq!(values, vec![10, 12]);
+        assert_eq!(
+            LazyDeltas::<i64>::from_host(raw.as_mut_ptr(), raw.len())
+                .unwrap()
+                .deltas
+                .len(),
+            2
+        );
+
+        let mut invalid = encode(vec![raw_delta(Operation::Update as i32, "abc")]);
+        let err = Deltas::<DeltaInt64>::from_host(invalid.as_mut_ptr(), invalid.len()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DeltaError>(),
+            Some(DeltaError::InvalidValue { field: "new", .. })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "delta for key pool:0x01 has unknown operation 42")]
+    fn deltas_new_panics_on_unknown_operation() {
+        Deltas::<DeltaInt64>::new(vec![raw_delta(42, "1")]);
+    }
 }
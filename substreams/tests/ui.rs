@@ -0,0 +1,5 @@
+#[test]
+fn handler_macros_ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,7 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::{StoreGetBigInt, StoreSetBigInt};
+
+#[substreams::handlers::store]
+fn store_prices(clock: Clock, prices: StoreGetBigInt, prices_idx: u32, store: StoreSetBigInt) {}
+
+fn main() {}
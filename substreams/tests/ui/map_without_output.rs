@@ -0,0 +1,6 @@
+use substreams::pb::substreams::Clock;
+
+#[substreams::handlers::map]
+fn map_blocks(clock: Clock) {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use substreams::pb::substreams::Clock;
+
+#[substreams::handlers::store]
+fn store_blocks(clock: Clock) {}
+
+fn main() {}
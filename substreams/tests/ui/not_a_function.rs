@@ -0,0 +1,4 @@
+#[substreams::handlers::map]
+struct MapPools;
+
+fn main() {}
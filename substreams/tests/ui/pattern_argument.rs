@@ -0,0 +1,8 @@
+use substreams::pb::substreams::Clock;
+
+#[substreams::handlers::map]
+fn map_clock(Clock { number, .. }: Clock) -> Clock {
+    unimplemented!()
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::StoreAddInt64;
+
+#[substreams::handlers::store]
+fn store_blocks(clock: Clock, store: StoreAddInt64) -> u64 {
+    clock.number
+}
+
+fn main() {}
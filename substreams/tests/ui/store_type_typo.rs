@@ -0,0 +1,8 @@
+use substreams::pb::substreams::Clock;
+
+#[substreams::handlers::map]
+fn map_prices(clock: Clock, prices: substreams::store::StoreGetBigint) -> Clock {
+    clock
+}
+
+fn main() {}
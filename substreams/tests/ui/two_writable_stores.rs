@@ -0,0 +1,7 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::{StoreAddInt64, StoreSetInt64};
+
+#[substreams::handlers::store]
+fn store_blocks(clock: Clock, counts: StoreAddInt64, latest: StoreSetInt64) {}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use substreams::pb::substreams::Clock;
+
+#[substreams::handlers::map(outpt = "proto:sf.substreams.v1.Clock")]
+fn map_clock(clock: Clock) -> Clock {
+    clock
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::StoreSetInt64;
+
+#[substreams::handlers::store(update_policy = "add", value_type = "int64")]
+fn store_blocks(_clock: Clock, _store: StoreSetInt64) {}
+
+fn main() {}
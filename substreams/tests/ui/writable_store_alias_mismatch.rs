@@ -0,0 +1,9 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::StoreSetInt64;
+
+type Blocks = StoreSetInt64;
+
+#[substreams::handlers::store(update_policy = "add", value_type = "int64")]
+fn store_blocks(_clock: Clock, _store: Blocks) {}
+
+fn main() {}
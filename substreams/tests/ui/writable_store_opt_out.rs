@@ -0,0 +1,7 @@
+use substreams::pb::substreams::Clock;
+use substreams::store::StoreAddInt64;
+
+#[substreams::handlers::store(writable_store = "none")]
+fn store_blocks(clock: Clock, store: StoreAddInt64) {}
+
+fn main() {}
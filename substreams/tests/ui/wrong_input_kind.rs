@@ -0,0 +1,10 @@
+use substreams::pb::substreams::Clock;
+
+struct Deltas;
+
+#[substreams::handlers::map]
+fn map_deltas(clock: Clock, deltas: Deltas) -> Clock {
+    clock
+}
+
+fn main() {}